@@ -0,0 +1,219 @@
+use crate::rect::Rect;
+use crate::{Message, Result};
+use gif::{Encoder, Frame, Repeat};
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Black stars on a white background.
+const PALETTE: &[u8] = &[0xff, 0xff, 0xff, 0x00, 0x00, 0x00];
+
+/// How the stars moving in and out of the message are exported.
+#[derive(Debug, Clone)]
+pub struct Animation {
+    /// Pixels per cell, in both directions.
+    pub scale: usize,
+    /// Seconds between frames.
+    pub time_step: u32,
+    /// Seconds before and after the message that are shown, rounded down to whole time steps.
+    pub window: u32,
+    /// Cells around the message, the rest of the sky is cropped.
+    pub margin: i32,
+}
+
+impl Default for Animation {
+    fn default() -> Self {
+        Animation {
+            scale: 4,
+            time_step: 1,
+            window: 10,
+            margin: 10,
+        }
+    }
+}
+
+/// One frame of the animation, with the second it shows relative to the message.
+struct Snapshot {
+    second: i32,
+    cells: Vec<Vec<bool>>,
+}
+
+impl Animation {
+    fn area(&self, message: &Message) -> Result<Rect> {
+        let bounds = message
+            .frame
+            .bounds()
+            .ok_or("Can't animate a sky without any stars")?;
+
+        Ok(Rect {
+            x_min: bounds.x_min.saturating_sub(self.margin),
+            y_min: bounds.y_min.saturating_sub(self.margin),
+            x_max: bounds.x_max.saturating_add(self.margin),
+            y_max: bounds.y_max.saturating_add(self.margin),
+        })
+    }
+
+    fn snapshots(&self, message: &Message, area: &Rect) -> Result<Vec<Snapshot>> {
+        if self.time_step == 0 || self.scale == 0 {
+            return err!("Time step and scale have to be positive");
+        }
+
+        // Every frame's second is at most `window` away, so they all fit once that does.
+        let window = match i32::try_from(self.window) {
+            Ok(window) => window,
+            Err(_) => return err!("A window of {} seconds is too long", self.window),
+        };
+        // Steps longer than the window only leave the message itself.
+        let time_step = i32::try_from(self.time_step).unwrap_or(i32::MAX);
+
+        // Frames are counted from the message, so it is always one of them.
+        let steps = window / time_step;
+
+        let mut snapshots = vec![];
+        for second in (-steps..=steps).map(|k| k * time_step) {
+            let frame = match message.frame.snapshot_at(second) {
+                Some(frame) => frame,
+                None => {
+                    return err!(
+                        "The stars leave the i32 range {} seconds from the message",
+                        second
+                    )
+                }
+            };
+
+            snapshots.push(Snapshot {
+                second,
+                cells: frame.render(area),
+            });
+        }
+
+        Ok(snapshots)
+    }
+
+    /// Pixel rows of a snapshot, scaled up, 1 for a star and 0 for the sky.
+    fn pixels(&self, snapshot: &Snapshot) -> Vec<Vec<u8>> {
+        let mut pixels = vec![];
+
+        for row in snapshot.cells.iter() {
+            let scaled: Vec<u8> = row
+                .iter()
+                .flat_map(|star| vec![*star as u8; self.scale])
+                .collect();
+
+            for _ in 0..self.scale {
+                pixels.push(scaled.clone());
+            }
+        }
+
+        pixels
+    }
+
+    /// Writes a looping GIF, lingering on the message itself.
+    pub fn write_gif(&self, message: &Message, w: impl Write) -> Result<()> {
+        let area = self.area(message)?;
+        let width = area.width() as usize * self.scale;
+        let height = area.height() as usize * self.scale;
+
+        if width > usize::from(u16::MAX) || height > usize::from(u16::MAX) {
+            return err!("A {}x{} animation is too large for a GIF", width, height);
+        }
+
+        let mut encoder = Encoder::new(w, width as u16, height as u16, PALETTE)?;
+        encoder.set_repeat(Repeat::Infinite)?;
+
+        for snapshot in self.snapshots(message, &area)? {
+            let pixels: Vec<u8> = self.pixels(&snapshot).concat();
+            let mut frame = Frame::from_indexed_pixels(width as u16, height as u16, pixels, None);
+            // In hundredths of a second.
+            frame.delay = if snapshot.second == 0 { 200 } else { 10 };
+            encoder.write_frame(&frame)?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes every frame as a numbered plain PBM image into `dir`, returning their paths.
+    pub fn write_pbm_frames(
+        &self,
+        message: &Message,
+        dir: impl AsRef<Path>,
+    ) -> Result<Vec<PathBuf>> {
+        let area = self.area(message)?;
+        let mut paths = vec![];
+
+        for (i, snapshot) in self.snapshots(message, &area)?.iter().enumerate() {
+            let path = dir.as_ref().join(format!("frame_{:04}.pbm", i));
+            let mut f = BufWriter::new(File::create(&path)?);
+
+            let pixels = self.pixels(snapshot);
+            writeln!(f, "P1")?;
+            writeln!(
+                f,
+                "# second {}",
+                i64::from(message.second) + i64::from(snapshot.second)
+            )?;
+            writeln!(f, "{} {}", pixels[0].len(), pixels.len())?;
+            for row in pixels {
+                let row: Vec<String> = row.iter().map(|pixel| pixel.to_string()).collect();
+                writeln!(f, "{}", row.join(" "))?;
+            }
+
+            paths.push(path);
+        }
+
+        Ok(paths)
+    }
+}
+
+#[test]
+fn test_animation() {
+    let input =
+        crate::read_input_from_file(concat!(env!("CARGO_MANIFEST_DIR"), "/input/test")).unwrap();
+    let message = crate::part1(&input).unwrap();
+
+    let animation = Animation {
+        scale: 2,
+        time_step: 2,
+        window: 3,
+        margin: 1,
+    };
+
+    let mut gif = vec![];
+    animation.write_gif(&message, &mut gif).unwrap();
+    assert!(gif.starts_with(b"GIF89a"));
+
+    let dir = std::env::temp_dir().join(format!("day10-frames-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let paths = animation.write_pbm_frames(&message, &dir).unwrap();
+
+    // Seconds -2, 0 and 2 around the message, which is 10x8 plus the margin.
+    assert_eq!(paths.len(), 3);
+    let frames: Vec<String> = paths
+        .iter()
+        .map(|path| std::fs::read_to_string(path).unwrap())
+        .collect();
+    let lines: Vec<&str> = frames[0].lines().collect();
+    assert_eq!(lines[..3], ["P1", "# second 1", "24 20"]);
+    assert_eq!(lines.len(), 3 + 20);
+
+    // The middle frame is the message itself.
+    let lines: Vec<&str> = frames[1].lines().collect();
+    assert_eq!(lines[1], "# second 3");
+    let stars = lines[3..]
+        .iter()
+        .map(|row| row.matches('1').count())
+        .sum::<usize>();
+    let cells = message.frame.render(&message.frame.bounds().unwrap());
+    let message_stars = cells.iter().flatten().filter(|star| **star).count();
+    assert_eq!(stars, message_stars * 2 * 2);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert!(Animation {
+        time_step: 0,
+        ..Animation::default()
+    }
+    .write_gif(&message, vec![])
+    .is_err());
+}
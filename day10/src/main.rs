@@ -1,23 +1,29 @@
 use lazy_static::lazy_static;
-use log::{debug, log};
 use regex::Regex;
 use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::env;
 use std::error::Error;
 use std::fmt::Display;
 use std::fmt::Formatter;
 use std::fs::File;
 use std::io::BufReader;
 use std::io::Read;
-use std::io::Write;
 use std::path::Path;
 use std::path::PathBuf;
 use std::str::FromStr;
 
 macro_rules! err {
-    ($($tt:tt)*) => { Err(Box::<Error>::from(format!($($tt)*))) }
+    ($($tt:tt)*) => { Err(Box::<dyn ::std::error::Error>::from(format!($($tt)*))) }
 }
 
-type Result<T> = std::result::Result<T, Box<std::error::Error>>;
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+mod animation;
+mod ocr;
+mod rect;
+
+use crate::rect::Rect;
 
 #[derive(Debug, PartialOrd, PartialEq, Clone)]
 struct Coordinate {
@@ -28,9 +34,27 @@ struct Coordinate {
 }
 
 impl Coordinate {
-    pub fn advance(&mut self) {
-        self.x += self.vx;
-        self.y += self.vy;
+    /// The star moved `dt` seconds, backwards if it's negative.
+    /// `None` if it leaves the i32 range by then.
+    pub fn step_by(&self, dt: i32) -> Option<Coordinate> {
+        let (x, y) = self.position_at(dt)?;
+        Some(Coordinate { x, y, ..*self })
+    }
+
+    /// Where the star is `t` seconds from now, or was for negative `t`.
+    /// `None` if that's outside of the i32 range.
+    pub fn position_at(&self, t: i32) -> Option<(i32, i32)> {
+        let x = self.vx.checked_mul(t)?.checked_add(self.x)?;
+        let y = self.vy.checked_mul(t)?.checked_add(self.y)?;
+        Some((x, y))
+    }
+
+    /// Same as `position_at`, but wide enough to never overflow.
+    fn wide_position_at(&self, t: i64) -> (i128, i128) {
+        (
+            i128::from(self.x) + i128::from(self.vx) * i128::from(t),
+            i128::from(self.y) + i128::from(self.vy) * i128::from(t),
+        )
     }
 }
 
@@ -44,61 +68,76 @@ impl CoordinateGrid {
         CoordinateGrid { coordinates }
     }
 
+    #[cfg(test)]
     pub fn advance(&mut self) {
-        self.coordinates.iter_mut().for_each(|c| c.advance())
+        *self = self.snapshot_at(1).expect("Stars stay in range");
     }
 
-    pub fn bounds(&self) -> (i32, i32) {
-        let x_max = self.coordinates.iter().max_by_key(|c| c.x).unwrap().x;
-        let y_max = self.coordinates.iter().max_by_key(|c| c.y).unwrap().y;
-        let x_min = self.coordinates.iter().min_by_key(|c| c.x).unwrap().x;
-        let y_min = self.coordinates.iter().min_by_key(|c| c.y).unwrap().y;
-
-        let x_range = x_max.abs() - x_min.abs() + 1;
-        let y_range = y_max.abs() - y_min.abs() + 1;
+    /// The stars as they are `t` seconds from now, leaving these where they are.
+    /// `None` if any of them leaves the i32 range by then.
+    pub fn snapshot_at(&self, t: i32) -> Option<CoordinateGrid> {
+        let coordinates = self
+            .coordinates
+            .iter()
+            .map(|c| c.step_by(t))
+            .collect::<Option<_>>()?;
+        Some(CoordinateGrid::from_coordiantes(coordinates))
+    }
 
-        (x_range, y_range)
+    /// Width and height of the bounding box at `t`, computed without moving any star.
+    fn extent_at(&self, t: i64) -> (i128, i128) {
+        let positions = self.coordinates.iter().map(|c| c.wide_position_at(t));
+        let (x_min, y_min, x_max, y_max) = positions.fold(
+            (i128::MAX, i128::MAX, i128::MIN, i128::MIN),
+            |(x_min, y_min, x_max, y_max), (x, y)| {
+                (x_min.min(x), y_min.min(y), x_max.max(x), y_max.max(y))
+            },
+        );
+
+        (x_max - x_min + 1, y_max - y_min + 1)
     }
-}
 
-impl Display for CoordinateGrid {
-    //noinspection ALL
-    fn fmt(&self, f: &mut Formatter) -> ::std::result::Result<(), ::std::fmt::Error> {
-        let x_max = self.coordinates.iter().max_by_key(|c| c.x).unwrap().x;
-        let y_max = self.coordinates.iter().max_by_key(|c| c.y).unwrap().y;
-        let x_min = self.coordinates.iter().min_by_key(|c| c.x).unwrap().x;
-        let y_min = self.coordinates.iter().min_by_key(|c| c.y).unwrap().y;
+    /// Area of the smallest box holding every star.
+    #[cfg(test)]
+    pub fn bounding_box_area(&self) -> i64 {
+        self.bounds().map_or(0, |bounds| bounds.area())
+    }
 
-        let x_normalized = x_max.abs() - x_min.abs() + 1;
-        let y_normalized = y_max.abs() - y_min.abs() + 1;
+    /// The smallest box holding every star, `None` if there are none.
+    pub fn bounds(&self) -> Option<Rect> {
+        Rect::bounding(self.coordinates.iter().map(|c| (c.x, c.y)))
+    }
 
-        let mut grid = vec![vec![0; y_normalized as usize]; x_normalized as usize];
+    /// Which cells of `area` hold a star, row by row. Stars outside of it are left out.
+    pub fn render(&self, area: &Rect) -> Vec<Vec<bool>> {
+        let mut rows = vec![vec![false; area.width() as usize]; area.height() as usize];
 
         for c in self.coordinates.iter() {
-            let normalized_x = if c.x <= 0 {
-                (c.x + x_min.abs()) as usize
-            } else {
-                (c.x - x_min.abs()) as usize
-            };
-
-            let normalized_y = if c.y <= 0 {
-                (c.y + y_min.abs()) as usize
-            } else {
-                (c.y - y_min.abs()) as usize
-            };
-
-            grid[normalized_x][normalized_y] = 1;
+            if let Some((x, y)) = area.offset(c.x, c.y) {
+                rows[y][x] = true;
+            }
         }
 
-        for row in 0..y_normalized as usize {
-            for value in 0..x_normalized as usize {
-                if grid[value][row] == 1 {
+        rows
+    }
+}
+
+impl Display for CoordinateGrid {
+    fn fmt(&self, f: &mut Formatter) -> ::std::result::Result<(), ::std::fmt::Error> {
+        let bounds = match self.bounds() {
+            Some(bounds) => bounds,
+            None => return Ok(()),
+        };
+
+        for row in self.render(&bounds) {
+            for star in row {
+                if star {
                     write!(f, "# ")?
                 } else {
                     write!(f, ". ")?
                 }
             }
-            write!(f, "\n")?
+            writeln!(f)?
         }
 
         Ok(())
@@ -106,7 +145,7 @@ impl Display for CoordinateGrid {
 }
 
 impl FromStr for Coordinate {
-    type Err = Box<Error>;
+    type Err = Box<dyn Error>;
 
     fn from_str(s: &str) -> Result<Self> {
         lazy_static! {
@@ -129,50 +168,176 @@ impl FromStr for Coordinate {
     }
 }
 
-fn part1(input: &str, n_seconds: u32, box_size: u32) -> Result<()> {
-    let mut coordinates = vec![];
+/// The stars at the second they line up into a message.
+#[derive(Debug)]
+struct Message {
+    second: i32,
+    frame: CoordinateGrid,
+}
 
-    for line in input.lines() {
-        coordinates.push(Coordinate::from_str(line)?);
+/// Finds the second where the stars' bounding box is smallest, which is when they spell out the
+/// message. That second can also be before the starting one.
+fn find_message(coordinates: Vec<Coordinate>) -> Result<Message> {
+    if coordinates.is_empty() {
+        return err!("Can't find a message without any stars");
     }
 
-    let mut board = CoordinateGrid::from_coordiantes(coordinates);
-
-    let mut attempts = 0;
-    let mut seconds = 0;
+    let sky = CoordinateGrid::from_coordiantes(coordinates);
+
+    // Width and height are each the difference of a maximum and a minimum of lines, so both are
+    // convex in time, and so is their sum. A ternary search finds where it's smallest.
+    let perimeter_at = |t: i64| {
+        let (width, height) = sky.extent_at(t);
+        width + height
+    };
+
+    // Each width is smallest where two stars cross, which is never further away in time than
+    // the stars are from each other.
+    let furthest = sky
+        .coordinates
+        .iter()
+        .map(|c| i64::from(c.x).abs().max(i64::from(c.y).abs()))
+        .max()
+        .unwrap_or(0);
+    let (mut low, mut high) = (-2 * furthest - 1, 2 * furthest + 1);
+
+    while high - low > 2 {
+        let third = (high - low) / 3;
+        let (a, b) = (low + third, high - third);
+
+        match perimeter_at(a).cmp(&perimeter_at(b)) {
+            Ordering::Less => high = b,
+            Ordering::Greater => low = a,
+            Ordering::Equal => {
+                low = a;
+                high = b;
+            }
+        }
+    }
 
-    while attempts <= 5 && seconds <= n_seconds {
-        let (x, y) = board.bounds();
+    let mut second = (low..=high)
+        .min_by_key(|t| perimeter_at(*t))
+        .expect("The search range is never empty");
+    // Prefer the current second when the stars keep their shape, e.g. when none of them move.
+    if perimeter_at(0) == perimeter_at(second) {
+        second = 0;
+    }
 
-        if x as u32 <= box_size && y as u32 <= box_size {
-            attempts += 1;
-            println!("{}", seconds);
-            println!("{}", board);
+    // The area can still shrink for a bit after the perimeter stopped doing so.
+    let area_at = |t: i64| {
+        let (width, height) = sky.extent_at(t);
+        width * height
+    };
+    for direction in [-1, 1].iter() {
+        while area_at(second + direction) < area_at(second) {
+            second += direction;
         }
-
-        seconds += 1;
-        board.advance();
     }
 
-    if attempts == 0 {
-        return err!("Didn't find any message");
+    let second = match i32::try_from(second) {
+        Ok(second) => second,
+        Err(_) => return err!("The message appears at second {}, too far away", second),
+    };
+
+    let frame = match sky.snapshot_at(second) {
+        Some(frame) => frame,
+        None => return err!("The stars leave the i32 range by second {}", second),
+    };
+
+    Ok(Message { second, frame })
+}
+
+fn part1(input: &str) -> Result<Message> {
+    let mut coordinates = vec![];
+
+    for line in input.lines() {
+        coordinates.push(Coordinate::from_str(line)?);
     }
 
-    Ok(())
+    find_message(coordinates)
 }
 
 #[test]
 fn test_part1() {
     let test_input =
-        read_input_from_file("/Users/omerba/Workspace/AOC2018/day10/input/test").unwrap();
+        read_input_from_file(concat!(env!("CARGO_MANIFEST_DIR"), "/input/test")).unwrap();
 
-    part1(&test_input, 3, 10).unwrap();
+    let message = part1(&test_input).unwrap();
+    assert_eq!(message.second, 3);
+    assert_eq!(message.frame.bounding_box_area(), 10 * 8);
+}
+
+#[test]
+fn test_message_in_the_past() {
+    let test_input =
+        read_input_from_file(concat!(env!("CARGO_MANIFEST_DIR"), "/input/test")).unwrap();
+
+    let mut message = part1(&test_input).unwrap();
+    for _ in 0..10 {
+        message.frame.advance();
+    }
+
+    let rewound = find_message(message.frame.coordinates).unwrap();
+    assert_eq!(rewound.second, -10);
+    assert_eq!(rewound.frame.bounding_box_area(), 10 * 8);
+}
+
+#[test]
+fn test_time_travel() {
+    let star = Coordinate::from_str("position=< 3, -2> velocity=<-1,  1>").unwrap();
+    assert_eq!(star.position_at(0), Some((3, -2)));
+    assert_eq!(star.position_at(5), Some((-2, 3)));
+    assert_eq!(star.position_at(-5), Some((8, -7)));
+    assert_eq!(
+        star.step_by(7).and_then(|moved| moved.step_by(-7)),
+        Some(star.clone())
+    );
+
+    // Stars that leave the i32 range have no position, rather than wrapping around.
+    assert_eq!(star.position_at(i32::MIN), None);
+    assert_eq!(
+        star.position_at(i32::MAX),
+        Some((3 - i32::MAX, -2 + i32::MAX))
+    );
+    let edge = CoordinateGrid::from_coordiantes(vec![
+        star.clone(),
+        Coordinate {
+            x: i32::MAX,
+            y: 0,
+            vx: 1,
+            vy: 0,
+        },
+    ]);
+    assert!(edge.snapshot_at(-1).is_some());
+    assert_eq!(edge.snapshot_at(1), None);
+
+    let test_input =
+        read_input_from_file(concat!(env!("CARGO_MANIFEST_DIR"), "/input/test")).unwrap();
+    let message = part1(&test_input).unwrap();
+
+    // Snapshots leave the grid alone, and agree with stepping one second at a time.
+    let mut sky = message.frame.snapshot_at(-3).unwrap();
+    assert_eq!(message.frame.bounding_box_area(), 10 * 8);
+    for _ in 0..3 {
+        sky.advance();
+    }
+    assert_eq!(sky, message.frame);
+
+    // Nothing moves, so the message is already there.
+    let still = find_message(vec![Coordinate {
+        x: 4,
+        y: -4,
+        vx: 0,
+        vy: 0,
+    }])
+    .unwrap();
+    assert_eq!(still.second, 0);
 }
 
 #[test]
 fn test_draw() {
     let test_input =
-        read_input_from_file("/Users/omerba/Workspace/AOC2018/day10/input/coords").unwrap();
+        read_input_from_file(concat!(env!("CARGO_MANIFEST_DIR"), "/input/coords")).unwrap();
 
     let mut coordinates = vec![];
 
@@ -182,7 +347,7 @@ fn test_draw() {
 
     let mut board = CoordinateGrid::from_coordiantes(coordinates);
 
-    for i in 0..10333 {
+    for _ in 0..10333 {
         board.advance();
     }
 
@@ -231,12 +396,27 @@ fn read_input_from_file(path: impl AsRef<Path>) -> Result<String> {
 
 fn main() -> Result<()> {
     let input = PathBuf::from("/Users/omerba/Workspace/AOC2018/day10/input/coords");
-    let f = File::open(input)?;
-    let mut f = BufReader::new(f);
+    let input = read_input_from_file(input)?;
 
-    let mut input = String::new();
+    let message = part1(&input)?;
+    match ocr::read_message(&message.frame) {
+        Ok(text) => println!("Part 1: {}", text),
+        Err(e) => println!("Part 1: {}\n{}", e, message.frame),
+    }
+    println!("Part 2: {}", message.second);
+
+    // Optionally animate the stars around the message, as a GIF or as a directory of PBM frames.
+    if let Some(output) = env::args().nth(1) {
+        let animation = animation::Animation::default();
+
+        if output.ends_with(".gif") {
+            animation.write_gif(&message, File::create(&output)?)?;
+        } else {
+            std::fs::create_dir_all(&output)?;
+            let frames = animation.write_pbm_frames(&message, &output)?;
+            println!("Wrote {} frames to {}", frames.len(), output);
+        }
+    }
 
-    f.read_to_string(&mut input)?;
-    part1(&input, 10334, 100)?;
     Ok(())
 }
@@ -0,0 +1,253 @@
+use crate::{CoordinateGrid, Result};
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+const GLYPH_WIDTH: i64 = 6;
+const GLYPH_HEIGHT: i64 = 10;
+/// Letters are separated by two empty columns.
+const GLYPH_STRIDE: i64 = GLYPH_WIDTH + 2;
+
+/// The letters of the puzzle's font which are known to show up in messages.
+const GLYPHS: &[(char, [&str; 10])] = &[
+    (
+        'A',
+        [
+            "..##..", ".#..#.", "#....#", "#....#", "#....#", "######", "#....#", "#....#",
+            "#....#", "#....#",
+        ],
+    ),
+    (
+        'B',
+        [
+            "#####.", "#....#", "#....#", "#....#", "#####.", "#....#", "#....#", "#....#",
+            "#....#", "#####.",
+        ],
+    ),
+    (
+        'C',
+        [
+            ".####.", "#....#", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....",
+            "#....#", ".####.",
+        ],
+    ),
+    (
+        'E',
+        [
+            "######", "#.....", "#.....", "#.....", "#####.", "#.....", "#.....", "#.....",
+            "#.....", "######",
+        ],
+    ),
+    (
+        'F',
+        [
+            "######", "#.....", "#.....", "#.....", "#####.", "#.....", "#.....", "#.....",
+            "#.....", "#.....",
+        ],
+    ),
+    (
+        'G',
+        [
+            ".####.", "#....#", "#.....", "#.....", "#.....", "#..###", "#....#", "#....#",
+            "#...##", ".###.#",
+        ],
+    ),
+    (
+        'H',
+        [
+            "#....#", "#....#", "#....#", "#....#", "######", "#....#", "#....#", "#....#",
+            "#....#", "#....#",
+        ],
+    ),
+    (
+        'J',
+        [
+            "...###", "....#.", "....#.", "....#.", "....#.", "....#.", "....#.", "#...#.",
+            "#...#.", ".###..",
+        ],
+    ),
+    (
+        'K',
+        [
+            "#....#", "#...#.", "#..#..", "#.#...", "##....", "##....", "#.#...", "#..#..",
+            "#...#.", "#....#",
+        ],
+    ),
+    (
+        'L',
+        [
+            "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....",
+            "#.....", "######",
+        ],
+    ),
+    (
+        'N',
+        [
+            "#....#", "##...#", "##...#", "#.#..#", "#.#..#", "#..#.#", "#..#.#", "#...##",
+            "#...##", "#....#",
+        ],
+    ),
+    (
+        'P',
+        [
+            "#####.", "#....#", "#....#", "#....#", "#####.", "#.....", "#.....", "#.....",
+            "#.....", "#.....",
+        ],
+    ),
+    (
+        'R',
+        [
+            "#####.", "#....#", "#....#", "#....#", "#####.", "#..#..", "#...#.", "#...#.",
+            "#....#", "#....#",
+        ],
+    ),
+    (
+        'X',
+        [
+            "#....#", "#....#", ".#..#.", ".#..#.", "..##..", "..##..", ".#..#.", ".#..#.",
+            "#....#", "#....#",
+        ],
+    ),
+    (
+        'Z',
+        [
+            "######", ".....#", ".....#", "....#.", "...#..", "..#...", ".#....", "#.....",
+            "#.....", "######",
+        ],
+    ),
+];
+
+/// Some glyphs in the frame didn't match any known letter.
+#[derive(Debug, PartialEq)]
+pub struct UnrecognisedGlyphs {
+    /// 0-based positions of the glyphs in the message.
+    pub positions: Vec<usize>,
+    /// The message, with `?` in place of every unrecognised glyph.
+    pub partial: String,
+}
+
+impl Display for UnrecognisedGlyphs {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let positions: Vec<String> = self.positions.iter().map(|p| p.to_string()).collect();
+        write!(
+            f,
+            "Unrecognised glyphs at positions {} in `{}`",
+            positions.join(", "),
+            self.partial
+        )
+    }
+}
+
+impl Error for UnrecognisedGlyphs {}
+
+/// Reads the message in an aligned frame, which has to be a single row of 6×10 letters.
+pub fn read_message(frame: &CoordinateGrid) -> Result<String> {
+    let bounds = frame
+        .bounds()
+        .ok_or("Can't read a message without any stars")?;
+    let (width, height) = (bounds.width(), bounds.height());
+
+    if height != GLYPH_HEIGHT || (width + 2) % GLYPH_STRIDE != 0 {
+        return err!(
+            "A {}x{} frame isn't a row of {}x{} letters",
+            width,
+            height,
+            GLYPH_WIDTH,
+            GLYPH_HEIGHT
+        );
+    }
+
+    let stars = frame.render(&bounds);
+    let mut message = String::new();
+    let mut unrecognised = vec![];
+
+    for position in 0..((width + 2) / GLYPH_STRIDE) as usize {
+        let left = position * GLYPH_STRIDE as usize;
+        let glyph = GLYPHS.iter().find(|(_, rows)| {
+            rows.iter().enumerate().all(|(y, row)| {
+                row.chars()
+                    .enumerate()
+                    .all(|(x, pixel)| (pixel == '#') == stars[y][left + x])
+            })
+        });
+
+        match glyph {
+            Some((letter, _)) => message.push(*letter),
+            None => {
+                message.push('?');
+                unrecognised.push(position);
+            }
+        }
+    }
+
+    if !unrecognised.is_empty() {
+        return Err(Box::new(UnrecognisedGlyphs {
+            positions: unrecognised,
+            partial: message,
+        }));
+    }
+
+    Ok(message)
+}
+
+#[cfg(test)]
+fn grid_from_art(art: &[&str]) -> CoordinateGrid {
+    let mut coordinates = vec![];
+
+    for (y, row) in art.iter().enumerate() {
+        for (x, pixel) in row.chars().enumerate() {
+            if pixel == '#' {
+                coordinates.push(crate::Coordinate {
+                    x: x as i32 - 100,
+                    y: y as i32 + 7,
+                    vx: 0,
+                    vy: 0,
+                });
+            }
+        }
+    }
+
+    CoordinateGrid::from_coordiantes(coordinates)
+}
+
+#[test]
+fn test_read_message() {
+    let input =
+        crate::read_input_from_file(concat!(env!("CARGO_MANIFEST_DIR"), "/input/coords")).unwrap();
+    let message = crate::part1(&input).unwrap();
+    assert_eq!(read_message(&message.frame).unwrap(), "AHZLLCAL");
+
+    // Every known letter, shifted away from the origin.
+    let art: Vec<String> = (0..10)
+        .map(|y| {
+            let row: Vec<&str> = GLYPHS.iter().map(|(_, rows)| rows[y]).collect();
+            row.join("..")
+        })
+        .collect();
+    let art: Vec<&str> = art.iter().map(|row| row.as_str()).collect();
+    assert_eq!(
+        read_message(&grid_from_art(&art)).unwrap(),
+        "ABCEFGHJKLNPRXZ"
+    );
+}
+
+#[test]
+fn test_unrecognised_glyphs() {
+    let mut art = GLYPHS[0].1.map(|row| format!("{}..######..{}", row, row));
+    art[3] = format!("{}..#.##.#..{}", GLYPHS[0].1[3], GLYPHS[0].1[3]);
+    let art: Vec<&str> = art.iter().map(|row| row.as_str()).collect();
+
+    let e = read_message(&grid_from_art(&art)).unwrap_err();
+    assert_eq!(
+        *e.downcast::<UnrecognisedGlyphs>().unwrap(),
+        UnrecognisedGlyphs {
+            positions: vec![1],
+            partial: "A?A".to_owned()
+        }
+    );
+
+    // The example's letters are only 8 high.
+    let input =
+        crate::read_input_from_file(concat!(env!("CARGO_MANIFEST_DIR"), "/input/test")).unwrap();
+    let message = crate::part1(&input).unwrap();
+    assert!(read_message(&message.frame).is_err());
+}
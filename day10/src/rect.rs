@@ -0,0 +1,137 @@
+/// An axis aligned box, with both corners inclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x_min: i32,
+    pub y_min: i32,
+    pub x_max: i32,
+    pub y_max: i32,
+}
+
+impl Rect {
+    /// The smallest box holding every point, `None` if there are none.
+    pub fn bounding(points: impl IntoIterator<Item = (i32, i32)>) -> Option<Rect> {
+        points.into_iter().fold(None, |rect, (x, y)| {
+            Some(match rect {
+                None => Rect {
+                    x_min: x,
+                    y_min: y,
+                    x_max: x,
+                    y_max: y,
+                },
+                Some(r) => Rect {
+                    x_min: r.x_min.min(x),
+                    y_min: r.y_min.min(y),
+                    x_max: r.x_max.max(x),
+                    y_max: r.y_max.max(y),
+                },
+            })
+        })
+    }
+
+    // Widths are i64, since a box spanning the whole i32 range is wider than any i32.
+    pub fn width(&self) -> i64 {
+        i64::from(self.x_max) - i64::from(self.x_min) + 1
+    }
+
+    pub fn height(&self) -> i64 {
+        i64::from(self.y_max) - i64::from(self.y_min) + 1
+    }
+
+    #[cfg(test)]
+    pub fn area(&self) -> i64 {
+        self.width() * self.height()
+    }
+
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        (self.x_min..=self.x_max).contains(&x) && (self.y_min..=self.y_max).contains(&y)
+    }
+
+    /// The position of `(x, y)` relative to the top left corner, if it's inside the box.
+    pub fn offset(&self, x: i32, y: i32) -> Option<(usize, usize)> {
+        if self.contains(x, y) {
+            Some((
+                (i64::from(x) - i64::from(self.x_min)) as usize,
+                (i64::from(y) - i64::from(self.y_min)) as usize,
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+use crate::{Coordinate, CoordinateGrid};
+#[cfg(test)]
+use proptest::prelude::*;
+#[cfg(test)]
+use std::collections::HashSet;
+
+/// Coordinates which are all negative, all positive, or both.
+#[cfg(test)]
+fn axis() -> impl Strategy<Value = std::ops::Range<i32>> {
+    prop_oneof![Just(-50..0), Just(0..50), Just(-25..25), Just(-50..-40)]
+}
+
+#[cfg(test)]
+fn grid() -> impl Strategy<Value = CoordinateGrid> {
+    (axis(), axis())
+        .prop_flat_map(|(xs, ys)| prop::collection::vec((xs, ys), 1..40))
+        .prop_map(|points| {
+            CoordinateGrid::from_coordiantes(
+                points
+                    .into_iter()
+                    .map(|(x, y)| Coordinate { x, y, vx: 0, vy: 0 })
+                    .collect(),
+            )
+        })
+}
+
+#[cfg(test)]
+proptest! {
+    #[test]
+    fn test_bounds_are_tight(grid in grid()) {
+        let bounds = grid.bounds().unwrap();
+        let xs: Vec<i32> = grid.coordinates.iter().map(|c| c.x).collect();
+        let ys: Vec<i32> = grid.coordinates.iter().map(|c| c.y).collect();
+
+        prop_assert_eq!(bounds.x_min, *xs.iter().min().unwrap());
+        prop_assert_eq!(bounds.x_max, *xs.iter().max().unwrap());
+        prop_assert_eq!(bounds.y_min, *ys.iter().min().unwrap());
+        prop_assert_eq!(bounds.y_max, *ys.iter().max().unwrap());
+        prop_assert!(grid.coordinates.iter().all(|c| bounds.contains(c.x, c.y)));
+    }
+
+    #[test]
+    fn test_render_places_every_star(grid in grid()) {
+        let bounds = grid.bounds().unwrap();
+        let rows = grid.render(&bounds);
+
+        prop_assert_eq!(rows.len() as i64, bounds.height());
+        prop_assert!(rows.iter().all(|row| row.len() as i64 == bounds.width()));
+
+        let stars: HashSet<(i32, i32)> = grid.coordinates.iter().map(|c| (c.x, c.y)).collect();
+        let lit = rows.iter().flatten().filter(|star| **star).count();
+        prop_assert_eq!(lit, stars.len());
+
+        for (x, y) in stars {
+            let (column, row) = bounds.offset(x, y).unwrap();
+            prop_assert!(rows[row][column]);
+        }
+
+        let rendered = grid.to_string();
+        prop_assert_eq!(rendered.lines().count() as i64, bounds.height());
+        prop_assert_eq!(rendered.matches('#').count(), lit);
+    }
+}
+
+#[test]
+fn test_mixed_signs() {
+    let bounds = Rect::bounding(vec![(-5, 3), (5, -3)]).unwrap();
+    assert_eq!((bounds.width(), bounds.height()), (11, 7));
+    assert_eq!(bounds.offset(-5, -3), Some((0, 0)));
+    assert_eq!(bounds.offset(6, 0), None);
+
+    let extremes = Rect::bounding(vec![(i32::MIN, 0), (i32::MAX, 0)]).unwrap();
+    assert_eq!(extremes.width(), 1 << 32);
+    assert_eq!(Rect::bounding(vec![]), None);
+}
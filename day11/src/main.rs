@@ -1,18 +1,20 @@
-use nalgebra::base::Dynamic;
+use nalgebra::base::Dyn;
 use nalgebra::base::VecStorage;
-use nalgebra::MatrixSlice3;
-use nalgebra::{Matrix, U3, U5};
+use nalgebra::Matrix;
+#[cfg(test)]
+use rand::{rngs::StdRng, RngExt, SeedableRng};
+use std::env;
+use std::error::Error;
 use std::fs::File;
-use std::io::Write;
+use std::io::{BufReader, Read};
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
 
-type Grid = Matrix<i32, Dynamic, Dynamic, VecStorage<i32, Dynamic, Dynamic>>;
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+type Grid = Matrix<i32, Dyn, Dyn, VecStorage<i32, Dyn, Dyn>>;
 
 fn hundreath_digit(n: i32) -> i32 {
-    let mut hundreath = n / 100;
-    if hundreath > 10 {
-        hundreath = hundreath % 10;
-    }
-    hundreath
+    (n / 100 % 10).abs()
 }
 
 #[test]
@@ -21,25 +23,37 @@ fn test_hundreath() {
     assert_eq!(hundreath_digit(500), 5);
     assert_eq!(hundreath_digit(5000), 0);
     assert_eq!(hundreath_digit(7111), 1);
+    assert_eq!(hundreath_digit(1000), 0);
+    assert_eq!(hundreath_digit(12_345), 3);
+    assert_eq!(hundreath_digit(-1234), 2);
 }
 
 struct PowerGrid {
+    #[cfg_attr(not(test), allow(dead_code))]
     pub grid: Grid,
     pub summed_area_table: Grid,
+    pub width: usize,
+    pub height: usize,
 }
 
 impl PowerGrid {
+    #[cfg(test)]
     pub fn with_serial_number(serial_number: i32) -> Self {
-        let mut grid = Grid::zeros(300, 300);
+        PowerGrid::new(serial_number, 300, 300)
+    }
+
+    /// A grid of `width` by `height` fuel cells, with (1, 1) at the top left.
+    pub fn new(serial_number: i32, width: usize, height: usize) -> Self {
+        let mut grid = Grid::zeros(width, height);
 
         for (x, mut row) in grid.row_iter_mut().enumerate() {
-            for (y, mut col) in row.iter_mut().enumerate() {
+            for (y, col) in row.iter_mut().enumerate() {
                 let x_pos = (x + 1) as i32;
                 let y_pos = (y + 1) as i32;
                 let rack_id = x_pos + 10;
                 let power_level = rack_id * y_pos;
                 let with_serial_number = power_level + serial_number;
-                let multiplied_by_rack_id = (with_serial_number * rack_id) as i32;
+                let multiplied_by_rack_id = with_serial_number * rack_id;
                 // Integer div
                 let hundreath_digit = hundreath_digit(multiplied_by_rack_id);
                 let power_cell_value = hundreath_digit - 5;
@@ -53,6 +67,8 @@ impl PowerGrid {
         PowerGrid {
             grid,
             summed_area_table,
+            width,
+            height,
         }
     }
 
@@ -77,82 +93,276 @@ impl PowerGrid {
         new
     }
 
-    pub fn max3x3(&self) -> (i32, i32, i32) {
-        let mut max = 0;
-        let mut max_x = 0;
-        let mut max_y = 0;
-        for x in 2..300 {
-            for y in 2..300 {
-                let mut sum = *self.summed_area_table.index((x, y));
-                if x >= 3 {
-                    let subtract_x = *self.summed_area_table.index((x - 3, y));
-                    sum -= subtract_x;
-                };
-                if y >= 3 {
-                    let subtract_y = *self.summed_area_table.index((x, y - 3));
-                    sum -= subtract_y;
-                }
-                if x >= 3 && y >= 3 {
-                    let subtracted_twice = *self.summed_area_table.index((x - 3, y - 3));
-                    sum += subtracted_twice;
-                }
+    /// Total power of the `size` square whose top left cell is at 0-based `(x, y)`.
+    fn square_power(&self, x: usize, y: usize, size: usize) -> i32 {
+        let table = &self.summed_area_table;
+        let (right, bottom) = (x + size - 1, y + size - 1);
 
-                if sum > max {
-                    max = sum;
-                    max_x = x;
-                    max_y = y;
+        let mut sum = table[(right, bottom)];
+        if x > 0 {
+            sum -= table[(x - 1, bottom)];
+        }
+        if y > 0 {
+            sum -= table[(right, y - 1)];
+        }
+        if x > 0 && y > 0 {
+            sum += table[(x - 1, y - 1)];
+        }
+        sum
+    }
+
+    /// The best 3x3 square as `(power, x, y)`, with its 1-based top left corner.
+    pub fn max3x3(&self) -> Option<(i32, i32, i32)> {
+        self.max_any(3..=3).map(|(max, _, x, y)| (max, x, y))
+    }
+
+    /// The best square of any size in `sizes` as `(power, size, x, y)`, with its 1-based top left
+    /// corner. Sizes that don't fit in the grid are skipped, `None` if none of them do.
+    /// Ties go to the smallest size, then the smallest `x`, then the smallest `y`.
+    pub fn max_any(&self, sizes: RangeInclusive<usize>) -> Option<(i32, i32, i32, i32)> {
+        let mut best: Option<(i32, usize, usize, usize)> = None;
+
+        let largest = self.width.min(self.height);
+
+        for sz in (*sizes.start()).max(1)..=(*sizes.end()).min(largest) {
+            for x in 0..=self.width - sz {
+                for y in 0..=self.height - sz {
+                    let sum = self.square_power(x, y, sz);
+
+                    if best.is_none_or(|(max, _, _, _)| sum > max) {
+                        best = Some((sum, sz, x, y));
+                    }
                 }
             }
         }
-        // Coordinates are for bottom right, 0-based.
-        // To convert to top left, add 'max_sz' + 1 + 1.
-        let max_x_top_left = max_x - 3 + 2;
-        let max_y_top_left = max_y - 3 + 2;
-        (max, (max_x_top_left) as i32, (max_y_top_left) as i32)
+
+        best.map(|(max, sz, x, y)| (max, sz as i32, x as i32 + 1, y as i32 + 1))
     }
+}
 
-    pub fn max_any(&self) -> (i32, i32, i32, i32) {
-        let mut max = 0;
-        let mut max_sz = 0;
-        let mut max_x = 0;
-        let mut max_y = 0;
-
-        for sz in 3..=300 {
-            for x in sz..300 - (sz - 1){
-                for y in sz..300 - (sz - 1) {
-                    let mut sum = *self.summed_area_table.index((x, y));
-                    if x >= sz  {
-                        let subtract_x = *self.summed_area_table.index((x - sz, y));
-                        sum -= subtract_x;
-                    };
-                    if y >= sz  {
-                        let subtract_y = *self.summed_area_table.index((x, y - sz));
-                        sum -= subtract_y;
-                    }
-                    if x >= sz && y >= sz {
-                        let subtracted_twice = *self.summed_area_table.index((x - sz, y - sz));
-                        sum += subtracted_twice;
-                    }
+fn parse_serial_number(input: &str) -> Result<i32> {
+    input
+        .trim()
+        .parse()
+        .map_err(|e| From::from(format!("Invalid serial number `{}`: {}", input.trim(), e)))
+}
+
+#[test]
+fn test_parse_serial_number() {
+    assert_eq!(parse_serial_number("1718\n").unwrap(), 1718);
+    assert!(parse_serial_number("serial").is_err());
+}
+
+/// Command line arguments, `[serial] [--width=N] [--height=N] [--sizes=MIN-MAX]`.
+#[derive(Debug, PartialEq)]
+struct Options {
+    /// Read from the input file when not given.
+    serial_number: Option<i32>,
+    width: usize,
+    height: usize,
+    /// Square sizes searched in part 2.
+    sizes: RangeInclusive<usize>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            serial_number: None,
+            width: 300,
+            height: 300,
+            sizes: 1..=300,
+        }
+    }
+}
+
+fn parse_number(name: &str, value: &str) -> Result<usize> {
+    value
+        .parse()
+        .map_err(|e| From::from(format!("Invalid {} `{}`: {}", name, value, e)))
+}
+
+/// Either a single size, or an inclusive range like `3-20`.
+fn parse_sizes(value: &str) -> Result<RangeInclusive<usize>> {
+    let (start, end) = match value.split_once('-') {
+        Some((start, end)) => (parse_number("size", start)?, parse_number("size", end)?),
+        None => {
+            let size = parse_number("size", value)?;
+            (size, size)
+        }
+    };
+
+    if start > end {
+        return Err(From::from(format!("Empty size range `{}`", value)));
+    }
+
+    Ok(start..=end)
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Options> {
+    let mut options = Options::default();
+
+    for arg in args {
+        if let Some(width) = arg.strip_prefix("--width=") {
+            options.width = parse_number("width", width)?;
+        } else if let Some(height) = arg.strip_prefix("--height=") {
+            options.height = parse_number("height", height)?;
+        } else if let Some(sizes) = arg.strip_prefix("--sizes=") {
+            options.sizes = parse_sizes(sizes)?;
+        } else if arg.starts_with("--") {
+            return Err(From::from(format!("Unknown option `{}`", arg)));
+        } else if options.serial_number.is_none() {
+            options.serial_number = Some(parse_serial_number(&arg)?);
+        } else {
+            return Err(From::from(format!("Unexpected argument `{}`", arg)));
+        }
+    }
+
+    Ok(options)
+}
+
+#[test]
+fn test_parse_args() {
+    let args = |args: &[&str]| parse_args(args.iter().map(|arg| arg.to_string()));
+
+    assert_eq!(args(&[]).unwrap(), Options::default());
+    assert_eq!(
+        args(&["--sizes=3-20", "-18", "--height=40", "--width=20"]).unwrap(),
+        Options {
+            serial_number: Some(-18),
+            width: 20,
+            height: 40,
+            sizes: 3..=20,
+        }
+    );
+    assert_eq!(args(&["--sizes=16"]).unwrap().sizes, 16..=16);
+
+    assert!(args(&["--sizes=20-3"]).is_err());
+    assert!(args(&["--width=-1"]).is_err());
+    assert!(args(&["--size=3"]).is_err());
+    assert!(args(&["18", "42"]).is_err());
+}
+
+#[test]
+fn test_grid_dimensions() {
+    let grid = PowerGrid::new(18, 40, 20);
+    assert_eq!(grid.grid.shape(), (40, 20));
+
+    // Cells are the same no matter the size of the grid around them.
+    let full = PowerGrid::with_serial_number(18);
+    assert_eq!(grid.grid[(32, 19)], full.grid[(32, 19)]);
+
+    let (_, size, _, _) = grid.max_any(3..=300).unwrap();
+    assert!(size <= 20);
+    assert_eq!(grid.max_any(21..=300), None);
+}
+
+#[test]
+fn test_power_levels() {
+    assert_eq!(PowerGrid::with_serial_number(8).grid[(2, 4)], 4);
+    assert_eq!(PowerGrid::with_serial_number(57).grid[(121, 78)], -5);
+    assert_eq!(PowerGrid::with_serial_number(39).grid[(216, 195)], 0);
+    assert_eq!(PowerGrid::with_serial_number(71).grid[(100, 152)], 4);
+}
+
+#[test]
+fn test_examples() {
+    let grid = PowerGrid::with_serial_number(18);
+    assert_eq!(grid.max3x3(), Some((29, 33, 45)));
+    assert_eq!(grid.max_any(1..=300), Some((113, 16, 90, 269)));
+
+    let grid = PowerGrid::with_serial_number(42);
+    assert_eq!(grid.max3x3(), Some((30, 21, 61)));
+    assert_eq!(grid.max_any(1..=300), Some((119, 12, 232, 251)));
+}
 
-                    if sum > max {
-                        max = sum;
-                        max_sz = sz;
-                        max_x = x;
-                        max_y = y;
+#[cfg(test)]
+fn brute_force_max(grid: &PowerGrid, sizes: RangeInclusive<usize>) -> Option<(i32, i32, i32, i32)> {
+    let mut best = None;
+
+    for sz in sizes {
+        for x in 0..grid.width {
+            for y in 0..grid.height {
+                if x + sz > grid.width || y + sz > grid.height {
+                    continue;
+                }
+
+                let mut sum = 0;
+                for dx in 0..sz {
+                    for dy in 0..sz {
+                        sum += grid.grid[(x + dx, y + dy)];
                     }
                 }
+
+                if best.is_none_or(|(max, _, _, _)| sum > max) {
+                    best = Some((sum, sz as i32, x as i32 + 1, y as i32 + 1));
+                }
             }
         }
-        // Coordinates are for bottom right, 0-based.
-        // To convert to top left, add 'max_sz' + 1 + 1.
-        let max_x_top_left = max_x - max_sz + 2;
-        let max_y_top_left = max_y - max_sz + 2;
-        (max, max_sz as i32, (max_x_top_left) as i32, (max_y_top_left) as i32)
     }
+
+    best
+}
+
+#[test]
+fn test_against_brute_force() {
+    // Seeded, so every run checks the same serials and grid sizes.
+    let mut rng = StdRng::seed_from_u64(11);
+
+    for _ in 0..50 {
+        let serial_number = rng.random_range(0..10_000);
+        let width = rng.random_range(1..16);
+        let height = rng.random_range(1..16);
+        let grid = PowerGrid::new(serial_number, width, height);
+
+        // Sizes 1 and 2, and windows touching every edge, are all candidates.
+        assert_eq!(
+            grid.max_any(1..=16),
+            brute_force_max(&grid, 1..=16),
+            "serial {} on a {}x{} grid",
+            serial_number,
+            width,
+            height
+        );
+        assert_eq!(
+            grid.max3x3(),
+            brute_force_max(&grid, 3..=3).map(|(max, _, x, y)| (max, x, y))
+        );
+    }
+
+    // Every square is negative, so the best one still has to be found.
+    let mut grid = PowerGrid::new(18, 5, 5);
+    grid.grid.fill(-1);
+    grid.summed_area_table = PowerGrid::summed_area_table(&grid.grid);
+    assert_eq!(grid.max_any(1..=5), Some((-1, 1, 1, 1)));
+    assert_eq!(grid.max3x3(), Some((-9, 1, 1)));
 }
 
-fn main() {
-    let grid = PowerGrid::with_serial_number(1718);
-    println!("{:?}", grid.max3x3());
-    println!("{:?}", grid.max_any());
+fn main() -> Result<()> {
+    let options = parse_args(env::args().skip(1))?;
+
+    // The serial number is either given on the command line, or read from a file.
+    let serial_number = match options.serial_number {
+        Some(serial_number) => serial_number,
+        None => {
+            let input = PathBuf::from("/Users/omerba/Workspace/AOC2018/day11/input/serial");
+            let f = File::open(input)?;
+            let mut f = BufReader::new(f);
+
+            let mut input = String::new();
+
+            f.read_to_string(&mut input)?;
+            parse_serial_number(&input)?
+        }
+    };
+
+    let grid = PowerGrid::new(serial_number, options.width, options.height);
+
+    let (_, x, y) = grid.max3x3().ok_or("The grid is smaller than 3x3")?;
+    println!("Part 1: {},{}", x, y);
+
+    let (_, size, x, y) = grid
+        .max_any(options.sizes)
+        .ok_or("None of the square sizes fit in the grid")?;
+    println!("Part 2: {},{},{}", x, y, size);
+    Ok(())
 }
@@ -1,8 +1,9 @@
+mod render;
+
 use lazy_static::lazy_static;
 use regex::Regex;
 
 use std::collections::HashMap;
-use std::mem;
 use std::str::FromStr;
 use std::{
     fs::File,
@@ -18,44 +19,13 @@ use log::debug;
 use std::collections::HashSet;
 use std::fmt::Write;
 
-type Result<T> = ::std::result::Result<T, Box<::std::error::Error>>;
+type Result<T> = ::std::result::Result<T, Box<dyn ::std::error::Error>>;
 
 struct Grid {
     pub inner: Vec<Vec<char>>,
     pub origins: HashSet<Point>,
 }
 
-struct IterPoints<'a> {
-    grid: &'a Grid,
-    x: i32,
-    y: i32,
-}
-
-impl Iterator for IterPoints<'_> {
-    type Item = Point;
-
-    fn next(&mut self) -> Option<<Self as Iterator>::Item> {
-        let dim = self.grid.size() as i32;
-        if (self.x >= dim) & (self.y >= dim) {
-            return None;
-        }
-
-        let p = Point {
-            x: self.x,
-            y: self.y,
-        };
-
-        if self.x <= dim {
-            self.x += 1;
-        } else {
-            self.x = 0;
-            self.y += 1;
-        }
-
-        Some(p)
-    }
-}
-
 impl Grid {
     pub fn new(sz: usize, origins: &[Point]) -> Self {
         let mut matrix = Vec::with_capacity(sz);
@@ -81,18 +51,10 @@ impl Grid {
         }
     }
 
-    pub fn points(&self) -> IterPoints {
-        IterPoints {
-            grid: self,
-            x: 0,
-            y: 0,
-        }
-    }
-
     pub fn size(&self) -> usize {
         self.inner.len()
     }
-    
+
     pub fn set_value(&mut self, p: &Point, v: char) {
         self.inner[p.y as usize][p.x as usize] = v;
     }
@@ -151,7 +113,7 @@ struct Point {
 }
 
 impl FromStr for Point {
-    type Err = Box<::std::error::Error>;
+    type Err = Box<dyn ::std::error::Error>;
 
     fn from_str(s: &str) -> ::std::result::Result<Self, <Self as FromStr>::Err> {
         lazy_static! {
@@ -173,7 +135,7 @@ impl FromStr for Point {
 
 impl Point {
     fn neighbors(&self) -> Vec<Point> {
-        return vec![
+        vec![
             Point {
                 x: self.x,
                 y: self.y + 1,
@@ -190,7 +152,7 @@ impl Point {
                 x: self.x,
                 y: self.y - 1,
             },
-        ];
+        ]
     }
 
     fn manhattan_distance(&self, other: &Point) -> i32 {
@@ -239,37 +201,114 @@ fn round(
     temp
 }
 
-fn part2(input: &str, matrix_size: usize, cap: i32) -> Result<i32> {
-    let mut points = vec![];
+/// Total distance from every coordinate in a window to a set of 1-D positions.
+///
+/// Because manhattan distance separates into `|dx| + |dy|`, the total distance from a point
+/// to all origins is the sum of two of these, one per axis.
+struct AxisDistances {
+    start: i32,
+    sums: Vec<i64>,
+}
 
-    for line in input.lines() {
-        points.push(Point::from_str(line)?);
-    }
+impl AxisDistances {
+    /// Computes the sums for every coordinate whose total distance could be below `cap`.
+    ///
+    /// Once past the outermost position each step away adds `positions.len()` to the total,
+    /// so the window only has to extend `cap / positions.len()` beyond the extremes.
+    pub fn new(positions: &[i32], cap: i32) -> Self {
+        let mut sorted = positions.to_vec();
+        sorted.sort();
+
+        let mut prefix = Vec::with_capacity(sorted.len() + 1);
+        prefix.push(0_i64);
+        for p in sorted.iter() {
+            prefix.push(prefix.last().expect("Prefix is never empty") + i64::from(*p));
+        }
 
-    let mut matrix = Grid::new(matrix_size, &points);
-    let mut found = Vec::new();
-
-    for p in matrix.points() {
-        let mut total_distance = 0;
-        for origin in points.iter() {
-            total_distance += p.manhattan_distance(origin);
-            if total_distance > cap {
-                break;
+        let (min, max) = match (sorted.first(), sorted.last()) {
+            (Some(min), Some(max)) => (*min, *max),
+            _ => {
+                return AxisDistances {
+                    start: 0,
+                    sums: vec![],
+                }
             }
+        };
+
+        let n = sorted.len() as i64;
+        let margin = cap.max(0) / sorted.len() as i32 + 1;
+        let start = min - margin;
+        let end = max + margin;
+
+        let mut sums = Vec::with_capacity((end - start + 1) as usize);
+        for v in start..=end {
+            // Number of positions at or before `v`.
+            let k = sorted.partition_point(|p| *p <= v);
+            let v = i64::from(v);
+            let before = v * k as i64 - prefix[k];
+            let after = (prefix[sorted.len()] - prefix[k]) - v * (n - k as i64);
+            sums.push(before + after);
         }
 
-        if total_distance < cap {
-            debug!("{:?} -> {}", p, total_distance);
-            found.push(p)
+        AxisDistances { start, sums }
+    }
+
+    /// Total distance at coordinate `v`, or `None` if it is outside the computed window.
+    pub fn get(&self, v: i32) -> Option<i64> {
+        if v < self.start {
+            return None;
         }
+        self.sums.get((v - self.start) as usize).cloned()
     }
+}
+
+/// The region of points whose total distance to all origins is less than `cap`.
+struct SafeRegion {
+    xs: AxisDistances,
+    ys: AxisDistances,
+    cap: i64,
+}
 
-    for point in found.iter() {
-        matrix.set_value(point, '#');
+impl SafeRegion {
+    pub fn new(origins: &[Point], cap: i32) -> Self {
+        let xs: Vec<i32> = origins.iter().map(|p| p.x).collect();
+        let ys: Vec<i32> = origins.iter().map(|p| p.y).collect();
+
+        SafeRegion {
+            xs: AxisDistances::new(&xs, cap),
+            ys: AxisDistances::new(&ys, cap),
+            cap: i64::from(cap),
+        }
+    }
+
+    pub fn contains(&self, p: &Point) -> bool {
+        match (self.xs.get(p.x), self.ys.get(p.y)) {
+            (Some(dx), Some(dy)) => dx + dy < self.cap,
+            _ => false,
+        }
     }
-    debug!("\n{}", matrix.print()?);
 
-    Ok(found.len() as i32)
+    /// Counts the points in the region without visiting each of them.
+    pub fn size(&self) -> usize {
+        let mut ys = self.ys.sums.clone();
+        ys.sort();
+
+        self.xs
+            .sums
+            .iter()
+            .map(|dx| ys.partition_point(|dy| dx + dy < self.cap))
+            .sum()
+    }
+}
+
+fn part2(input: &str, cap: i32) -> Result<usize> {
+    let mut points = vec![];
+
+    for line in input.lines() {
+        points.push(Point::from_str(line)?);
+    }
+
+    Ok(SafeRegion::new(&points, cap).size())
 }
 
 fn part1(input: &str, matrix_size: usize) -> Result<i32> {
@@ -285,7 +324,7 @@ fn part1(input: &str, matrix_size: usize) -> Result<i32> {
 
     let mut letters_used = Vec::new();
 
-    for (point, letter) in points.iter().cloned().zip((b'a'..b'z').chain(b'A'..b'Z')) {
+    for (point, letter) in points.iter().cloned().zip((b'a'..=b'z').chain(b'A'..=b'Z')) {
         letters_used.push(char::from(letter));
         q.push((point, char::from(letter)))
     }
@@ -299,7 +338,7 @@ fn part1(input: &str, matrix_size: usize) -> Result<i32> {
             break;
         }
 
-        mem::replace(&mut points, new);
+        points = new;
     }
 
     debug!("Diverged: {:?}", diverging);
@@ -339,7 +378,24 @@ fn test_part2() {
 8, 9
 ";
 
-    assert_eq!(part2(test_input, 20, 32).unwrap(), 16);
+    assert_eq!(part2(test_input, 32).unwrap(), 16);
+}
+
+#[test]
+fn test_safe_region_outside_bounding_box() {
+    let origins = [Point { x: 0, y: 0 }, Point { x: 0, y: 0 }];
+    let region = SafeRegion::new(&origins, 6);
+
+    // |x| + |y| < 3 is a diamond of 13 points, all but one outside the origins' bounding box.
+    assert_eq!(region.size(), 13);
+    assert!(region.contains(&Point { x: -2, y: 0 }));
+    assert!(!region.contains(&Point { x: -2, y: 1 }));
+
+    let brute_force = (-10..=10)
+        .flat_map(|x| (-10..=10).map(move |y| Point { x, y }))
+        .filter(|p| origins.iter().map(|o| p.manhattan_distance(o)).sum::<i32>() < 6)
+        .count();
+    assert_eq!(brute_force, region.size());
 }
 
 fn main() -> Result<()> {
@@ -352,7 +408,29 @@ fn main() -> Result<()> {
     f.read_to_string(&mut input)?;
 
     println!("{}", part1(&input, 1000)?);
-    println!("{}", part2(&input, 1000, 10000)?);
+    println!("{}", part2(&input, 10000)?);
+
+    // Optionally render the regions, e.g. `day6 regions.svg` or `day6 regions.png`.
+    if let Some(output) = std::env::args().nth(1).map(PathBuf::from) {
+        let mut points = vec![];
+        for line in input.lines() {
+            points.push(Point::from_str(line)?);
+        }
+
+        let map = render::RegionMap::new(&points, 10);
+        let safe_region = SafeRegion::new(&points, 10000);
+
+        match output.extension().and_then(|ext| ext.to_str()) {
+            Some("svg") => std::fs::write(&output, render::to_svg(&map, Some(&safe_region), 4)?)?,
+            Some("png") => render::write_png(&map, Some(&safe_region), 4, File::create(&output)?)?,
+            _ => {
+                return Err(From::from(format!(
+                    "{} should end in .svg or .png",
+                    output.display()
+                )))
+            }
+        }
+    }
 
     Ok(())
 }
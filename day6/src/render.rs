@@ -0,0 +1,411 @@
+use crate::{Point, Result, SafeRegion};
+use std::collections::HashSet;
+use std::fmt::Write as FmtWrite;
+use std::io::Write;
+
+type Rgb = [u8; 3];
+
+const TIE_COLOR: Rgb = [0x9e, 0x9e, 0x9e];
+const MARKER_COLOR: Rgb = [0x00, 0x00, 0x00];
+const OUTLINE_COLOR: Rgb = [0xff, 0xff, 0xff];
+
+/// Nearest-origin ownership of every point in a window around the origins.
+pub struct RegionMap {
+    pub origins: Vec<Point>,
+    pub min_x: i32,
+    pub min_y: i32,
+    pub width: usize,
+    pub height: usize,
+    /// Index of the closest origin for every point, row major. `None` marks a tie.
+    owners: Vec<Option<usize>>,
+    /// Origins whose region touches the edge of the bounding box, and therefore never ends.
+    pub infinite: HashSet<usize>,
+}
+
+impl RegionMap {
+    /// Builds the map over the origins' bounding box, grown by `margin` on every side.
+    pub fn new(origins: &[Point], margin: i32) -> Self {
+        let min_x = origins.iter().map(|p| p.x).min().unwrap_or(0) - margin;
+        let max_x = origins.iter().map(|p| p.x).max().unwrap_or(0) + margin;
+        let min_y = origins.iter().map(|p| p.y).min().unwrap_or(0) - margin;
+        let max_y = origins.iter().map(|p| p.y).max().unwrap_or(0) + margin;
+
+        let width = (max_x - min_x + 1) as usize;
+        let height = (max_y - min_y + 1) as usize;
+
+        let mut owners = Vec::with_capacity(width * height);
+        let mut infinite = HashSet::new();
+
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                let p = Point { x, y };
+                let mut closest = None;
+                let mut closest_distance = i32::MAX;
+
+                for (i, origin) in origins.iter().enumerate() {
+                    let distance = p.manhattan_distance(origin);
+                    if distance < closest_distance {
+                        closest_distance = distance;
+                        closest = Some(i);
+                    } else if distance == closest_distance {
+                        closest = None;
+                    }
+                }
+
+                if let Some(owner) = closest {
+                    if x == min_x || x == max_x || y == min_y || y == max_y {
+                        infinite.insert(owner);
+                    }
+                }
+
+                owners.push(closest);
+            }
+        }
+
+        RegionMap {
+            origins: origins.to_vec(),
+            min_x,
+            min_y,
+            width,
+            height,
+            owners,
+            infinite,
+        }
+    }
+
+    /// The closest origin to `p`, `None` for ties or points outside the map.
+    pub fn owner(&self, p: &Point) -> Option<usize> {
+        let col = p.x - self.min_x;
+        let row = p.y - self.min_y;
+        if col < 0 || row < 0 || col as usize >= self.width || row as usize >= self.height {
+            return None;
+        }
+
+        self.owners[row as usize * self.width + col as usize]
+    }
+
+    fn point_at(&self, col: usize, row: usize) -> Point {
+        Point {
+            x: self.min_x + col as i32,
+            y: self.min_y + row as i32,
+        }
+    }
+}
+
+/// Spreads hues around the color wheel using the golden angle, so neighbouring indices differ.
+fn origin_color(index: usize) -> Rgb {
+    let hue = (index as f64 * 137.507_764) % 360.0;
+    hsl_to_rgb(hue, 0.65, 0.55)
+}
+
+fn hsl_to_rgb(hue: f64, saturation: f64, lightness: f64) -> Rgb {
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let h = hue / 60.0;
+    let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+
+    let (r, g, b) = match h as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+
+    let m = lightness - chroma / 2.0;
+    let to_byte = |v: f64| ((v + m) * 255.0).round() as u8;
+
+    [to_byte(r), to_byte(g), to_byte(b)]
+}
+
+fn darken(color: Rgb) -> Rgb {
+    [color[0] / 2, color[1] / 2, color[2] / 2]
+}
+
+fn hex(color: Rgb) -> String {
+    format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
+
+/// Renders the region map as SVG, one `scale`-sized square per point.
+pub fn to_svg(map: &RegionMap, safe_region: Option<&SafeRegion>, scale: u32) -> Result<String> {
+    let mut f = String::new();
+    let scale = scale.max(1) as usize;
+
+    writeln!(
+        f,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" shape-rendering="crispEdges">"#,
+        map.width * scale,
+        map.height * scale
+    )?;
+
+    writeln!(f, "<defs>")?;
+    writeln!(
+        f,
+        r#"<pattern id="hatch" width="{s}" height="{s}" patternUnits="userSpaceOnUse" patternTransform="rotate(45)"><line x1="0" y1="0" x2="0" y2="{s}" stroke="black" stroke-opacity="0.4" stroke-width="{w}"/></pattern>"#,
+        s = scale.max(2) * 2,
+        w = scale.max(2) / 2
+    )?;
+    writeln!(f, "</defs>")?;
+
+    for row in 0..map.height {
+        for col in 0..map.width {
+            let color = match map.owner(&map.point_at(col, row)) {
+                Some(owner) => origin_color(owner),
+                None => TIE_COLOR,
+            };
+
+            writeln!(
+                f,
+                r#"<rect x="{}" y="{}" width="{s}" height="{s}" fill="{}"/>"#,
+                col * scale,
+                row * scale,
+                hex(color),
+                s = scale
+            )?;
+        }
+    }
+
+    // Hatch over every point belonging to an infinite region.
+    for row in 0..map.height {
+        for col in 0..map.width {
+            match map.owner(&map.point_at(col, row)) {
+                Some(owner) if map.infinite.contains(&owner) => writeln!(
+                    f,
+                    r#"<rect x="{}" y="{}" width="{s}" height="{s}" fill="url(#hatch)"/>"#,
+                    col * scale,
+                    row * scale,
+                    s = scale
+                )?,
+                _ => {}
+            }
+        }
+    }
+
+    if let Some(region) = safe_region {
+        let mut path = String::new();
+
+        for (x1, y1, x2, y2) in safe_region_outline(map, region) {
+            write!(
+                path,
+                "M{} {}L{} {}",
+                x1 * scale,
+                y1 * scale,
+                x2 * scale,
+                y2 * scale
+            )?;
+        }
+
+        writeln!(
+            f,
+            r#"<path d="{}" fill="none" stroke="{}" stroke-width="{}"/>"#,
+            path,
+            hex(OUTLINE_COLOR),
+            (scale / 4).max(1)
+        )?;
+    }
+
+    for origin in map.origins.iter() {
+        writeln!(
+            f,
+            r#"<circle cx="{}" cy="{}" r="{}" fill="{}"/>"#,
+            (origin.x - map.min_x) as f64 * scale as f64 + scale as f64 / 2.0,
+            (origin.y - map.min_y) as f64 * scale as f64 + scale as f64 / 2.0,
+            (scale as f64 / 2.0).max(1.0),
+            hex(MARKER_COLOR)
+        )?;
+    }
+
+    writeln!(f, "</svg>")?;
+
+    Ok(f)
+}
+
+/// Unit-length segments, in map cells, separating safe points from unsafe ones.
+fn safe_region_outline(map: &RegionMap, region: &SafeRegion) -> Vec<(usize, usize, usize, usize)> {
+    let inside = |col: i64, row: i64| {
+        col >= 0
+            && row >= 0
+            && (col as usize) < map.width
+            && (row as usize) < map.height
+            && region.contains(&map.point_at(col as usize, row as usize))
+    };
+
+    let mut segments = vec![];
+
+    for row in 0..=map.height as i64 {
+        for col in 0..=map.width as i64 {
+            // Horizontal edge above (col, row).
+            if col < map.width as i64 && inside(col, row - 1) != inside(col, row) {
+                segments.push((col as usize, row as usize, col as usize + 1, row as usize));
+            }
+            // Vertical edge left of (col, row).
+            if row < map.height as i64 && inside(col - 1, row) != inside(col, row) {
+                segments.push((col as usize, row as usize, col as usize, row as usize + 1));
+            }
+        }
+    }
+
+    segments
+}
+
+/// Renders the region map as an RGB PNG, one `scale`-sized square per point.
+pub fn write_png(
+    map: &RegionMap,
+    safe_region: Option<&SafeRegion>,
+    scale: u32,
+    w: impl Write,
+) -> Result<()> {
+    let scale = scale.max(1) as usize;
+    let width = map.width * scale;
+    let height = map.height * scale;
+    let mut pixels = vec![0_u8; width * height * 3];
+
+    let mut put = |px: usize, py: usize, color: Rgb| {
+        if px < width && py < height {
+            let offset = (py * width + px) * 3;
+            pixels[offset..offset + 3].copy_from_slice(&color);
+        }
+    };
+
+    for row in 0..map.height {
+        for col in 0..map.width {
+            let owner = map.owner(&map.point_at(col, row));
+            let color = match owner {
+                Some(owner) => origin_color(owner),
+                None => TIE_COLOR,
+            };
+            let hatched = owner.is_some_and(|owner| map.infinite.contains(&owner));
+
+            for dy in 0..scale {
+                for dx in 0..scale {
+                    let (px, py) = (col * scale + dx, row * scale + dy);
+                    // Diagonal stripes every 4 pixels.
+                    if hatched && (px + py) % 4 == 0 {
+                        put(px, py, darken(color));
+                    } else {
+                        put(px, py, color);
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(region) = safe_region {
+        for (x1, y1, x2, y2) in safe_region_outline(map, region) {
+            for px in x1 * scale..=x2 * scale {
+                for py in y1 * scale..=y2 * scale {
+                    put(px, py, OUTLINE_COLOR);
+                }
+            }
+        }
+    }
+
+    let radius = (scale / 2).max(1) as i64;
+    for origin in map.origins.iter() {
+        let cx = ((origin.x - map.min_x) as usize * scale + scale / 2) as i64;
+        let cy = ((origin.y - map.min_y) as usize * scale + scale / 2) as i64;
+
+        for py in cy - radius..=cy + radius {
+            for px in cx - radius..=cx + radius {
+                if px >= 0 && py >= 0 && (px - cx).pow(2) + (py - cy).pow(2) <= radius.pow(2) {
+                    put(px as usize, py as usize, MARKER_COLOR);
+                }
+            }
+        }
+    }
+
+    let mut encoder = png::Encoder::new(w, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&pixels)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_region_map() {
+    let origins = [
+        Point { x: 1, y: 1 },
+        Point { x: 1, y: 6 },
+        Point { x: 8, y: 3 },
+        Point { x: 3, y: 4 },
+        Point { x: 5, y: 5 },
+        Point { x: 8, y: 9 },
+    ];
+    let map = RegionMap::new(&origins, 1);
+
+    let finite: Vec<usize> = (0..origins.len())
+        .filter(|i| !map.infinite.contains(i))
+        .collect();
+    assert_eq!(finite, vec![3, 4]);
+
+    assert_eq!(map.owner(&Point { x: 1, y: 3 }), Some(0));
+    assert_eq!(map.owner(&Point { x: 2, y: 3 }), Some(3));
+    // Equidistant from A and B in the puzzle's example.
+    assert_eq!(map.owner(&Point { x: 0, y: 4 }), None);
+    assert_eq!(map.owner(&Point { x: 5, y: 2 }), Some(4));
+
+    let region = SafeRegion::new(&origins, 32);
+    let svg = to_svg(&map, Some(&region), 4).unwrap();
+    assert!(svg.starts_with("<svg"));
+    assert_eq!(svg.matches("<circle").count(), origins.len());
+
+    // The top left corner of a point's square.
+    let corner = |p: &Point| ((p.x - map.min_x) * 4, (p.y - map.min_y) * 4);
+
+    // Exactly the points of infinite regions are hatched.
+    let hatched: Vec<Point> = svg
+        .lines()
+        .filter(|line| line.contains(r#"fill="url(#hatch)""#))
+        .map(|line| {
+            let fields: Vec<&str> = line.split('"').collect();
+            let (x, y): (usize, usize) = (fields[1].parse().unwrap(), fields[3].parse().unwrap());
+            map.point_at(x / 4, y / 4)
+        })
+        .collect();
+    let infinite_points = (0..map.height)
+        .flat_map(|row| (0..map.width).map(move |col| (col, row)))
+        .map(|(col, row)| map.point_at(col, row))
+        .filter(|p| {
+            map.owner(p)
+                .is_some_and(|owner| map.infinite.contains(&owner))
+        })
+        .count();
+    assert_eq!(hatched.len(), infinite_points);
+    assert!(hatched.iter().all(|p| map
+        .owner(p)
+        .is_some_and(|owner| map.infinite.contains(&owner))));
+
+    let (x, y) = corner(&Point { x: 0, y: 4 });
+    assert!(svg.contains(&format!(
+        r#"<rect x="{}" y="{}" width="4" height="4" fill="{}"/>"#,
+        x,
+        y,
+        hex(TIE_COLOR)
+    )));
+    let (x, y) = corner(&Point { x: 5, y: 2 });
+    assert!(svg.contains(&format!(
+        r#"<rect x="{}" y="{}" width="4" height="4" fill="{}"/>"#,
+        x,
+        y,
+        hex(origin_color(4))
+    )));
+
+    let outline = svg
+        .lines()
+        .find(|line| line.starts_with("<path d="))
+        .expect("The safe region is outlined");
+    assert!(outline.starts_with(r#"<path d="M"#));
+
+    // Like PNGs, SVGs are at least one pixel per point.
+    assert_eq!(
+        to_svg(&map, None, 0).unwrap(),
+        to_svg(&map, None, 1).unwrap()
+    );
+
+    let mut png = vec![];
+    write_png(&map, Some(&region), 4, &mut png).unwrap();
+    assert_eq!(&png[1..4], b"PNG");
+}
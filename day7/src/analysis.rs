@@ -0,0 +1,185 @@
+use crate::schedule::Durations;
+use crate::{topological_order_by, DependencyGraph, Result, Step};
+use petgraph::prelude::*;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+
+/// When a step can run, assuming unlimited workers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StepTiming {
+    pub node: NodeIndex,
+    pub step: Step,
+    pub duration: u64,
+    pub earliest_start: u64,
+    pub earliest_finish: u64,
+    pub latest_start: u64,
+    pub latest_finish: u64,
+}
+
+impl StepTiming {
+    /// How long the step can be delayed without delaying the whole project.
+    pub fn slack(&self) -> u64 {
+        self.latest_start - self.earliest_start
+    }
+
+    pub fn is_critical(&self) -> bool {
+        self.slack() == 0
+    }
+}
+
+/// Critical path method over the dependency graph.
+#[derive(Debug)]
+pub struct CriticalPathAnalysis {
+    /// Every step, in the lexicographic order `part1` produces.
+    pub steps: Vec<StepTiming>,
+    pub total_duration: u64,
+    /// A chain of dependent steps without slack, from a starting step to a final one.
+    pub critical_path: Vec<NodeIndex>,
+}
+
+pub fn analyze(graph: &DependencyGraph, durations: &Durations) -> Result<CriticalPathAnalysis> {
+    let order = topological_order_by(graph, Step::cmp)?;
+
+    let mut duration = HashMap::new();
+    for idx in order.iter() {
+        duration.insert(*idx, u64::from(durations.of(&graph[*idx])?));
+    }
+
+    // Forward pass, every step starts as soon as all of its dependencies finish.
+    let mut earliest_finish: HashMap<NodeIndex, u64> = HashMap::new();
+    for idx in order.iter() {
+        let start = graph
+            .neighbors_directed(*idx, Direction::Incoming)
+            .map(|dep| earliest_finish[&dep])
+            .max()
+            .unwrap_or(0);
+        earliest_finish.insert(*idx, start + duration[idx]);
+    }
+
+    let total_duration = earliest_finish.values().cloned().max().unwrap_or(0);
+
+    // Backward pass, every step finishes as late as its dependents allow.
+    let mut latest_start: HashMap<NodeIndex, u64> = HashMap::new();
+    for idx in order.iter().rev() {
+        let finish = graph
+            .neighbors_directed(*idx, Direction::Outgoing)
+            .map(|next| latest_start[&next])
+            .min()
+            .unwrap_or(total_duration);
+        latest_start.insert(*idx, finish - duration[idx]);
+    }
+
+    let steps: Vec<StepTiming> = order
+        .iter()
+        .map(|idx| StepTiming {
+            node: *idx,
+            step: graph[*idx].clone(),
+            duration: duration[idx],
+            earliest_start: earliest_finish[idx] - duration[idx],
+            earliest_finish: earliest_finish[idx],
+            latest_start: latest_start[idx],
+            latest_finish: latest_start[idx] + duration[idx],
+        })
+        .collect();
+
+    // Walk back from the step finishing last, through dependencies finishing just in time.
+    // Ties go to the lexicographically smallest step.
+    let mut critical_path = vec![];
+    let mut current = order
+        .iter()
+        .filter(|idx| earliest_finish[idx] == total_duration)
+        .min_by_key(|idx| &graph[**idx])
+        .cloned();
+
+    while let Some(idx) = current {
+        critical_path.push(idx);
+        let start = earliest_finish[&idx] - duration[&idx];
+        current = graph
+            .neighbors_directed(idx, Direction::Incoming)
+            .filter(|dep| earliest_finish[dep] == start)
+            .max_by_key(|dep| Reverse(&graph[*dep]));
+    }
+    critical_path.reverse();
+
+    Ok(CriticalPathAnalysis {
+        steps,
+        total_duration,
+        critical_path,
+    })
+}
+
+impl Display for CriticalPathAnalysis {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let width = self
+            .steps
+            .iter()
+            .map(|timing| timing.step.len())
+            .max()
+            .unwrap_or(0)
+            .max("Step".len());
+
+        writeln!(
+            f,
+            "{:<w$}  {:>8}  {:>8}  {:>8}  {:>8}  {:>8}  {:>5}",
+            "Step",
+            "Duration",
+            "ES",
+            "EF",
+            "LS",
+            "LF",
+            "Slack",
+            w = width
+        )?;
+
+        for timing in self.steps.iter() {
+            writeln!(
+                f,
+                "{:<w$}  {:>8}  {:>8}  {:>8}  {:>8}  {:>8}  {:>5}{}",
+                timing.step,
+                timing.duration,
+                timing.earliest_start,
+                timing.earliest_finish,
+                timing.latest_start,
+                timing.latest_finish,
+                timing.slack(),
+                if timing.is_critical() { "  *" } else { "" },
+                w = width
+            )?;
+        }
+
+        let path: Vec<&str> = self
+            .critical_path
+            .iter()
+            .filter_map(|idx| self.steps.iter().find(|timing| timing.node == *idx))
+            .map(|timing| timing.step.as_str())
+            .collect();
+
+        writeln!(f, "Total duration: {}", self.total_duration)?;
+        writeln!(f, "Critical path: {}", path.join(" -> "))
+    }
+}
+
+#[test]
+fn test_analyze() {
+    let graph = crate::build_dependency_graph(crate::EXAMPLE).unwrap();
+    let analysis = analyze(&graph, &Durations::Alphabetical { base: 0 }).unwrap();
+
+    assert_eq!(analysis.total_duration, 14);
+
+    let path: Vec<&str> = analysis
+        .critical_path
+        .iter()
+        .map(|idx| graph[*idx].as_str())
+        .collect();
+    assert_eq!(path, vec!["C", "F", "E"]);
+
+    let timing = |step: &str| analysis.steps.iter().find(|t| t.step == step).unwrap();
+
+    // A has to wait for C, and can slip by one second before D delays E.
+    let a = timing("A");
+    assert_eq!((a.earliest_start, a.latest_start, a.slack()), (3, 4, 1));
+    let b = timing("B");
+    assert_eq!((b.earliest_finish, b.latest_finish, b.slack()), (6, 9, 3));
+    assert!(timing("F").is_critical());
+}
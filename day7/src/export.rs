@@ -0,0 +1,236 @@
+use crate::analysis::analyze;
+use crate::schedule::Durations;
+use crate::{topological_order_by, DependencyGraph, Result, Step};
+use petgraph::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+const CRITICAL_COLOR: &str = "#d62728";
+
+#[derive(Default)]
+pub struct ExportOptions<'a> {
+    /// Drop every edge which is already implied by a longer chain of dependencies.
+    pub transitive_reduction: bool,
+    /// Used to find the critical path, every step takes one second when missing.
+    pub durations: Option<&'a Durations>,
+}
+
+/// Everything both exporters need, computed once.
+struct Annotated {
+    /// Position of every step in the lexicographic order, starting at 1.
+    position: HashMap<NodeIndex, usize>,
+    edges: Vec<(NodeIndex, NodeIndex)>,
+    critical_path: Vec<NodeIndex>,
+}
+
+impl Annotated {
+    fn new(graph: &DependencyGraph, options: &ExportOptions) -> Result<Self> {
+        let order = topological_order_by(graph, Step::cmp)?;
+
+        let mut edges: Vec<(NodeIndex, NodeIndex)> = graph
+            .edge_references()
+            .map(|e| (e.source(), e.target()))
+            .collect();
+
+        if options.transitive_reduction {
+            let redundant = transitive_edges(graph, &order);
+            edges.retain(|edge| !redundant.contains(edge));
+        }
+
+        // Keep the output stable across runs.
+        edges.sort_by(|a, b| (&graph[a.0], &graph[a.1]).cmp(&(&graph[b.0], &graph[b.1])));
+        edges.dedup();
+
+        Ok(Annotated {
+            position: order
+                .iter()
+                .enumerate()
+                .map(|(i, idx)| (*idx, i + 1))
+                .collect(),
+            critical_path: analyze(graph, options.durations.unwrap_or(&Durations::Uniform(1)))?
+                .critical_path,
+            edges,
+        })
+    }
+
+    fn nodes(&self) -> Vec<NodeIndex> {
+        let mut nodes: Vec<NodeIndex> = self.position.keys().cloned().collect();
+        nodes.sort_by_key(|idx| self.position[idx]);
+        nodes
+    }
+
+    fn is_critical_edge(&self, edge: &(NodeIndex, NodeIndex)) -> bool {
+        self.critical_path
+            .windows(2)
+            .any(|pair| (pair[0], pair[1]) == *edge)
+    }
+}
+
+/// Edges `u -> v` where `v` can also be reached from `u` through some other step.
+fn transitive_edges(
+    graph: &DependencyGraph,
+    order: &[NodeIndex],
+) -> HashSet<(NodeIndex, NodeIndex)> {
+    // Everything reachable from each step, filled in reverse topological order.
+    let mut descendants: HashMap<NodeIndex, HashSet<NodeIndex>> = HashMap::new();
+
+    for idx in order.iter().rev() {
+        let mut reachable = HashSet::new();
+        for next in graph.neighbors_directed(*idx, Direction::Outgoing) {
+            reachable.insert(next);
+            reachable.extend(descendants[&next].iter().cloned());
+        }
+        descendants.insert(*idx, reachable);
+    }
+
+    let mut redundant = HashSet::new();
+    for idx in order.iter() {
+        let children: HashSet<NodeIndex> = graph
+            .neighbors_directed(*idx, Direction::Outgoing)
+            .collect();
+
+        for child in children.iter() {
+            if children
+                .iter()
+                .any(|other| other != child && descendants[other].contains(child))
+            {
+                redundant.insert((*idx, *child));
+            }
+        }
+    }
+
+    redundant
+}
+
+fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Renders the graph in Graphviz DOT, e.g. for `dot -Tsvg`.
+pub fn to_dot(graph: &DependencyGraph, options: &ExportOptions) -> Result<String> {
+    let annotated = Annotated::new(graph, options)?;
+    let mut f = String::new();
+
+    writeln!(f, "digraph dependencies {{")?;
+    writeln!(f, "    rankdir=LR;")?;
+    writeln!(f, "    node [shape=box];")?;
+
+    for idx in annotated.nodes() {
+        let name = dot_escape(&graph[idx]);
+        write!(
+            f,
+            "    \"{}\" [label=\"{} ({})\"",
+            name, name, annotated.position[&idx]
+        )?;
+        if annotated.critical_path.contains(&idx) {
+            write!(f, ", color=\"{}\", penwidth=2", CRITICAL_COLOR)?;
+        }
+        writeln!(f, "];")?;
+    }
+
+    for edge in annotated.edges.iter() {
+        write!(
+            f,
+            "    \"{}\" -> \"{}\"",
+            dot_escape(&graph[edge.0]),
+            dot_escape(&graph[edge.1])
+        )?;
+        if annotated.is_critical_edge(edge) {
+            write!(f, " [color=\"{}\", penwidth=2]", CRITICAL_COLOR)?;
+        }
+        writeln!(f, ";")?;
+    }
+
+    writeln!(f, "}}")?;
+
+    Ok(f)
+}
+
+/// Renders the graph as a Mermaid flowchart, which can be embedded in markdown docs.
+pub fn to_mermaid(graph: &DependencyGraph, options: &ExportOptions) -> Result<String> {
+    let annotated = Annotated::new(graph, options)?;
+    let mut f = String::new();
+
+    // Step names may contain characters mermaid doesn't allow in ids, so only use them as labels.
+    let id = |idx: NodeIndex| format!("n{}", idx.index());
+
+    writeln!(f, "graph LR")?;
+
+    for idx in annotated.nodes() {
+        writeln!(
+            f,
+            "    {}[\"{} ({})\"]",
+            id(idx),
+            graph[idx].replace('"', "#quot;"),
+            annotated.position[&idx]
+        )?;
+    }
+
+    let mut critical_links = vec![];
+    for (i, edge) in annotated.edges.iter().enumerate() {
+        writeln!(f, "    {} --> {}", id(edge.0), id(edge.1))?;
+        if annotated.is_critical_edge(edge) {
+            critical_links.push(i.to_string());
+        }
+    }
+
+    if !annotated.critical_path.is_empty() {
+        writeln!(
+            f,
+            "    classDef critical stroke:{},stroke-width:3px;",
+            CRITICAL_COLOR
+        )?;
+        writeln!(
+            f,
+            "    class {} critical;",
+            annotated
+                .critical_path
+                .iter()
+                .map(|idx| id(*idx))
+                .collect::<Vec<_>>()
+                .join(",")
+        )?;
+    }
+
+    if !critical_links.is_empty() {
+        writeln!(
+            f,
+            "    linkStyle {} stroke:{},stroke-width:3px;",
+            critical_links.join(","),
+            CRITICAL_COLOR
+        )?;
+    }
+
+    Ok(f)
+}
+
+#[test]
+fn test_export() {
+    // The example, plus an edge implied by C -> F -> E.
+    let test_input = format!(
+        "{}Step C must be finished before step E can begin.\n",
+        crate::EXAMPLE
+    );
+    let graph = crate::build_dependency_graph(&test_input).unwrap();
+    let durations = Durations::Alphabetical { base: 0 };
+
+    let dot = to_dot(
+        &graph,
+        &ExportOptions {
+            transitive_reduction: true,
+            durations: Some(&durations),
+        },
+    )
+    .unwrap();
+
+    assert!(dot.contains("\"C\" [label=\"C (1)\", color=\"#d62728\", penwidth=2];"));
+    assert!(dot.contains("\"A\" [label=\"A (2)\"];"));
+    assert!(dot.contains("\"F\" -> \"E\" [color=\"#d62728\", penwidth=2];"));
+    assert!(dot.contains("\"A\" -> \"B\";"));
+    // Implied by C -> F -> E.
+    assert!(!dot.contains("\"C\" -> \"E\""));
+
+    let mermaid = to_mermaid(&graph, &ExportOptions::default()).unwrap();
+    assert!(mermaid.starts_with("graph LR\n"));
+    assert_eq!(mermaid.matches("-->").count(), 8);
+}
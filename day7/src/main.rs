@@ -1,26 +1,32 @@
 #[macro_use]
 extern crate lazy_static;
+
+mod analysis;
+mod export;
+mod orders;
+mod schedule;
+
 use log::debug;
 use std::{
-    collections::{HashMap, HashSet},
+    cmp::Ordering,
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, HashSet, VecDeque},
+    fmt::{self, Display, Formatter},
     fs::File,
     io::{BufReader, Read},
     path::PathBuf,
     str::FromStr,
 };
 
-use env_logger;
-use failure::{format_err, Error};
-use petgraph::graph::EdgeReference;
+use failure::{format_err, Error, Fail};
+use petgraph::algo::tarjan_scc;
 use petgraph::prelude::*;
-use petgraph::visit::IntoNodeReferences;
-use petgraph::visit::NodeRef;
 
-use colored::Colorize;
 use regex::Regex;
+use schedule::{simulate, Durations, Schedule};
 
 type Result<T> = ::std::result::Result<T, Error>;
-type Step = char;
+type Step = String;
 
 struct Dependency {
     pub name: Step,
@@ -35,100 +41,147 @@ impl FromStr for Dependency {
     fn from_str(s: &str) -> Result<Self> {
         lazy_static! {
             pub static ref RE: Regex =
-                Regex::new(r#"Step ([A-Z]) must be finished before step ([A-Z]) can begin\."#)
+                Regex::new(r#"^Step (\S+) must be finished before step (\S+) can begin\.$"#)
                     .expect("This should be a valid regex");
         }
 
-        if let Some(matches) = RE.captures(s) {
+        if let Some(matches) = RE.captures(s.trim()) {
             return Ok(Dependency {
-                name: matches[2].as_bytes()[0] as Step,
-                depends_on: matches[1].as_bytes()[0] as Step,
+                name: matches[2].to_owned(),
+                depends_on: matches[1].to_owned(),
             });
         };
 
-        Err(format_err!("Failed to match text"))
+        Err(format_err!("Failed to match text `{}`", s))
     }
 }
 
-macro_rules! print_edges {
-    ($prefix: expr, $edges: expr, $graph: expr) => {
-        debug!(
-            "{}: {:?}",
-            $prefix,
-            $edges
-                .iter()
-                .map(|e| format!("{} -> {}", $graph[e.source()], $graph[e.target()]))
-                .collect::<Vec<_>>()
-        );
-    };
+/// Returned when the dependencies can never all be satisfied.
+#[derive(Debug)]
+struct CycleError {
+    /// The steps around one cycle, starting and ending with the same step.
+    cycle: Vec<Step>,
+    /// Steps which no step without dependencies leads to.
+    unreachable: Vec<Step>,
 }
 
+impl Display for CycleError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Dependencies contain a cycle `{}`",
+            self.cycle.join(" -> ")
+        )?;
+
+        if !self.unreachable.is_empty() {
+            write!(
+                f,
+                ", steps unreachable from any starting step: {}",
+                self.unreachable.join(", ")
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Fail for CycleError {}
+
 fn build_dependency_graph(input: &str) -> Result<DependencyGraph> {
     let mut graph = DependencyGraph::new();
     let mut nodes = HashMap::new();
 
-    for c in b'A'..=b'Z' {
-        let idx = graph.add_node(c as char);
-        nodes.insert(c as char, idx);
-    }
-
-    for line in input.lines() {
+    for line in input.lines().filter(|line| !line.trim().is_empty()) {
         let edge = Dependency::from_str(line)?;
-        graph.add_edge(
-            *nodes.get(&edge.depends_on).expect("Indexed all letters"),
-            *nodes.get(&edge.name).expect("Indexed all letters"),
-            edge.name,
-        );
+        let source = *nodes
+            .entry(edge.depends_on.clone())
+            .or_insert_with(|| graph.add_node(edge.depends_on.clone()));
+        let target = *nodes
+            .entry(edge.name.clone())
+            .or_insert_with(|| graph.add_node(edge.name.clone()));
+
+        graph.add_edge(source, target, edge.name);
     }
 
-    graph.retain_nodes(|g, node| g.neighbors_undirected(node).next().is_some());
-
     let nodes_without_deps = find_nodes_without_dependencies(&graph);
 
-    if nodes_without_deps.is_empty() {
-        return Err(format_err!("Failed to find nodes to start"));
+    if let Some(cycle) = find_cycle(&graph) {
+        let reachable = find_reachable(&graph, &nodes_without_deps);
+        let mut unreachable: Vec<Step> = graph
+            .node_indices()
+            .filter(|idx| !reachable.contains(idx))
+            .map(|idx| graph[idx].clone())
+            .collect();
+        unreachable.sort();
+
+        // Start the cycle from its smallest step, so the report doesn't depend on node order.
+        let mut cycle: Vec<Step> = cycle[1..].iter().map(|idx| graph[*idx].clone()).collect();
+        let smallest = (0..cycle.len())
+            .min_by_key(|i| &cycle[*i])
+            .expect("A cycle has at least one step");
+        cycle.rotate_left(smallest);
+        cycle.push(cycle[0].clone());
+
+        return Err(CycleError { cycle, unreachable }.into());
     }
 
-    let dummy_head = graph.add_node('h');
-    let mut dummy_edges = vec![];
-
-    for node in nodes_without_deps.iter() {
-        dummy_edges.push(graph.add_edge(dummy_head, *node, graph[*node]));
+    if nodes_without_deps.is_empty() {
+        return Err(format_err!("Failed to find nodes to start"));
     }
 
     Ok(graph)
 }
 
-fn step_is_possible(
-    step_as_edge: EdgeReference<Step, u32>,
-    graph: &DependencyGraph,
-    visited: &HashSet<Step>,
-) -> bool {
-    let target = graph[step_as_edge.target()];
+/// Finds a cycle, returned as the steps along it with the first step repeated at the end.
+fn find_cycle(graph: &DependencyGraph) -> Option<Vec<NodeIndex>> {
+    for component in tarjan_scc(graph) {
+        let start = component[0];
 
-    let deps: Vec<_> = graph
-        .edges_directed(step_as_edge.target(), Direction::Incoming)
-        .collect();
+        if component.len() == 1 && graph.find_edge(start, start).is_none() {
+            continue;
+        }
 
-    debug!("  Checking {}", target);
-    let can_do = deps.iter().all(|dep| {
-        let dep_name = graph[dep.source()];
-        let ok = visited.contains(&dep_name);
-        debug!(
-            "  Checking dep {:?} -> ({})",
-            dep_name,
-            if ok { "Found" } else { "Not found" }
-        );
-        ok
-    });
+        // Shortest way back to `start` without leaving the strongly connected component.
+        let members: HashSet<NodeIndex> = component.iter().cloned().collect();
+        let mut came_from = HashMap::new();
+        let mut queue = VecDeque::new();
+        queue.push_back(start);
+
+        while let Some(current) = queue.pop_front() {
+            for next in graph.neighbors_directed(current, Direction::Outgoing) {
+                if next == start {
+                    let mut cycle = vec![start, current];
+                    let mut node = current;
+                    while let Some(prev) = came_from.get(&node) {
+                        cycle.push(*prev);
+                        node = *prev;
+                    }
+                    cycle.reverse();
+                    return Some(cycle);
+                }
 
-    if can_do {
-        debug!("{}", format!("{} is possible", target).green());
-    } else {
-        debug!("{}", format!("{} is not possible", target).red());
+                if members.contains(&next) && !came_from.contains_key(&next) {
+                    came_from.insert(next, current);
+                    queue.push_back(next);
+                }
+            }
+        }
     }
 
-    can_do
+    None
+}
+
+fn find_reachable(graph: &DependencyGraph, roots: &[NodeIndex]) -> HashSet<NodeIndex> {
+    let mut reachable = HashSet::new();
+    let mut stack = roots.to_vec();
+
+    while let Some(node) = stack.pop() {
+        if reachable.insert(node) {
+            stack.extend(graph.neighbors_directed(node, Direction::Outgoing));
+        }
+    }
+
+    reachable
 }
 
 fn find_nodes_without_dependencies(graph: &DependencyGraph) -> Vec<NodeIndex> {
@@ -146,72 +199,83 @@ fn find_nodes_without_dependencies(graph: &DependencyGraph) -> Vec<NodeIndex> {
 }
 
 fn part1(graph: &DependencyGraph) -> Result<String> {
-    let mut available_steps = Vec::new();
-    let mut result = String::new();
-    let mut visited = HashSet::new();
-
-    // Because we only check the target for it's dependencies,
-    // a dummy node is added to create edges which represent a starting point for the algorithm.
-    visited.insert('h');
-    let head = graph
-        .node_references()
-        .find(|n| *n.weight() == 'h')
-        .expect("Head should exist");
-
-    available_steps.extend(graph.edges(head.0));
-
-    while visited.len() < graph.node_count() {
-        while !available_steps.is_empty() {
-            debug!("So far (ordered): {}", result);
-            debug!("So far done: {:?}", visited);
-
-            // Sort steps lexicographically
-            available_steps.sort_by_key(|f| f.weight());
-
-            // Reverse the stack so the cheapest step will be on top
-            available_steps.reverse();
-            print_edges!("Steps", available_steps, graph);
-
-            let step = available_steps
-                .pop()
-                .expect("We've checked above that stack is non-empty");
-
-            let target = graph[step.target()];
-
-            // Redundant step, ignore it
-            if visited.contains(&target) {
-                debug!(
-                    "{} Already done, Skipping `{} -> {}`",
-                    graph[step.target()],
-                    graph[step.source()],
-                    graph[step.target()]
-                );
-            } else {
-                if step_is_possible(step, &graph, &visited) {
-                    debug!("{} Done", target);
-                    let next: Vec<_> = graph
-                        .edges_directed(step.target(), Direction::Outgoing)
-                        .collect();
-
-                    available_steps.extend(next);
-
-                    visited.insert(target);
-                    result.push(target);
-                }
-            }
+    Ok(order_steps_by(graph, Step::cmp)?.concat())
+}
+
+/// Orders the steps so that every step comes after its dependencies.
+/// When several steps are available at once, the smallest according to `compare` goes first.
+///
+/// This is Kahn's algorithm, with a min-heap holding the steps whose dependencies are all done.
+fn order_steps_by<F>(graph: &DependencyGraph, compare: F) -> Result<Vec<Step>>
+where
+    F: Fn(&Step, &Step) -> Ordering,
+{
+    Ok(topological_order_by(graph, compare)?
+        .into_iter()
+        .map(|idx| graph[idx].clone())
+        .collect())
+}
+
+/// Same as `order_steps_by`, but returns the steps' node indices.
+fn topological_order_by<F>(graph: &DependencyGraph, compare: F) -> Result<Vec<NodeIndex>>
+where
+    F: Fn(&Step, &Step) -> Ordering,
+{
+    // Rank every step once, so the heap doesn't need to know about `compare`.
+    let mut by_preference: Vec<NodeIndex> = graph.node_indices().collect();
+    by_preference.sort_by(|a, b| compare(&graph[*a], &graph[*b]));
+    let rank: HashMap<NodeIndex, usize> = by_preference
+        .iter()
+        .enumerate()
+        .map(|(rank, idx)| (*idx, rank))
+        .collect();
+
+    let mut remaining_dependencies: HashMap<NodeIndex, usize> = graph
+        .node_indices()
+        .map(|idx| (idx, graph.edges_directed(idx, Direction::Incoming).count()))
+        .collect();
+
+    let mut available_steps: BinaryHeap<Reverse<(usize, NodeIndex)>> =
+        find_nodes_without_dependencies(graph)
+            .into_iter()
+            .map(|idx| Reverse((rank[&idx], idx)))
+            .collect();
+
+    let mut result = Vec::with_capacity(graph.node_count());
 
-            print_edges!("With added steps", available_steps, graph);
-            debug!("---------------------------------");
+    while let Some(Reverse((_, step))) = available_steps.pop() {
+        debug!("{} Done", graph[step]);
+        result.push(step);
+
+        for edge in graph.edges_directed(step, Direction::Outgoing) {
+            let deps = remaining_dependencies
+                .get_mut(&edge.target())
+                .expect("Every step is counted");
+            *deps -= 1;
+
+            if *deps == 0 {
+                available_steps.push(Reverse((rank[&edge.target()], edge.target())));
+            }
         }
     }
 
+    if result.len() < graph.node_count() {
+        return Err(format_err!(
+            "Ran out of available steps after {:?}, the dependencies contain a cycle",
+            result.iter().map(|idx| &graph[*idx]).collect::<Vec<_>>()
+        ));
+    }
+
     Ok(result)
 }
 
-#[test]
-fn test_part1() {
-    env_logger::init();
-    let test_input = "Step C must be finished before step A can begin.
+fn part2(graph: &DependencyGraph, workers: usize, durations: &Durations) -> Result<Schedule> {
+    simulate(graph, workers, durations, Step::cmp)
+}
+
+/// The dependencies from the puzzle's example.
+#[cfg(test)]
+const EXAMPLE: &str = "Step C must be finished before step A can begin.
 Step C must be finished before step F can begin.
 Step A must be finished before step B can begin.
 Step A must be finished before step D can begin.
@@ -219,11 +283,98 @@ Step B must be finished before step E can begin.
 Step D must be finished before step E can begin.
 Step F must be finished before step E can begin.
 ";
-    let graph = build_dependency_graph(test_input).unwrap();
+
+#[test]
+fn test_part1() {
+    env_logger::try_init().ok();
+    let graph = build_dependency_graph(EXAMPLE).unwrap();
 
     assert_eq!(part1(&graph).unwrap(), "CABDFE".to_owned());
 }
 
+#[test]
+fn test_part2() {
+    env_logger::try_init().ok();
+    let graph = build_dependency_graph(EXAMPLE).unwrap();
+
+    let schedule = part2(&graph, 2, &Durations::Alphabetical { base: 0 }).unwrap();
+    assert_eq!(schedule.total_time, 15);
+}
+
+#[test]
+fn test_string_steps() {
+    env_logger::try_init().ok();
+    let test_input = "Step fetch must be finished before step build-core can begin.
+Step build-core must be finished before step link can begin.
+Step build-ui must be finished before step link can begin.
+Step fetch must be finished before step build-ui can begin.
+";
+    let graph = build_dependency_graph(test_input).unwrap();
+
+    assert_eq!(
+        order_steps_by(&graph, Step::cmp).unwrap(),
+        vec!["fetch", "build-core", "build-ui", "link"]
+    );
+    assert_eq!(
+        order_steps_by(&graph, |a, b| b.cmp(a)).unwrap(),
+        vec!["fetch", "build-ui", "build-core", "link"]
+    );
+}
+
+#[test]
+fn test_step_named_like_old_head() {
+    env_logger::try_init().ok();
+    let test_input = "Step h must be finished before step a can begin.
+Step b must be finished before step a can begin.
+";
+    let graph = build_dependency_graph(test_input).unwrap();
+
+    assert_eq!(graph.node_count(), 3);
+    assert_eq!(part1(&graph).unwrap(), "bha");
+}
+
+#[test]
+fn test_cycle_detection() {
+    env_logger::try_init().ok();
+    let test_input = "Step R must be finished before step A can begin.
+Step A must be finished before step B can begin.
+Step B must be finished before step C can begin.
+Step C must be finished before step A can begin.
+Step X must be finished before step Y can begin.
+Step Y must be finished before step X can begin.
+Step Y must be finished before step Z can begin.
+";
+    let err = build_dependency_graph(test_input)
+        .unwrap_err()
+        .downcast::<CycleError>()
+        .unwrap();
+
+    assert_eq!(err.cycle, vec!["A", "B", "C", "A"]);
+    assert_eq!(err.unreachable, vec!["X", "Y", "Z"]);
+
+    // Discovered starting from C, but reported starting from its smallest step.
+    let err = build_dependency_graph(
+        "Step C must be finished before step A can begin.
+Step B must be finished before step C can begin.
+Step A must be finished before step B can begin.
+",
+    )
+    .unwrap_err()
+    .downcast::<CycleError>()
+    .unwrap();
+    assert_eq!(err.cycle, vec!["A", "B", "C", "A"]);
+
+    let err = build_dependency_graph("Step A must be finished before step A can begin.")
+        .unwrap_err()
+        .downcast::<CycleError>()
+        .unwrap();
+    assert_eq!(err.cycle, vec!["A", "A"]);
+    assert_eq!(
+        err.to_string(),
+        "Dependencies contain a cycle `A -> A`, steps unreachable from any starting step: A"
+    );
+}
+
 fn main() -> Result<()> {
     env_logger::init();
     let input = PathBuf::from("/Users/omerba/Workspace/AOC2018/day7/input/tasks");
@@ -235,7 +386,73 @@ fn main() -> Result<()> {
     f.read_to_string(&mut input)?;
     let graph = build_dependency_graph(&input)?;
 
+    // `--dot` or `--mermaid` print the graph instead, `--reduce` leaves out implied edges.
+    // `--orders` lists every valid order, `--count` counts them, `--check=A,B,..` validates one.
+    let (flags, paths): (Vec<String>, Vec<String>) = std::env::args()
+        .skip(1)
+        .partition(|arg| arg.starts_with("--"));
+
+    if let Some(flag) = flags.iter().find(|f| {
+        !["--dot", "--mermaid", "--reduce", "--orders", "--count"].contains(&f.as_str())
+            && !f.starts_with("--check=")
+    }) {
+        return Err(format_err!("Unknown option `{}`", flag));
+    }
+
+    // An optional table of `<step> <seconds>` lines replaces the alphabetical durations.
+    let durations = match paths.first() {
+        Some(path) => Durations::from_str(&std::fs::read_to_string(path)?)?,
+        None => Durations::Alphabetical { base: 60 },
+    };
+
+    let export_options = export::ExportOptions {
+        transitive_reduction: flags.iter().any(|f| f == "--reduce"),
+        durations: Some(&durations),
+    };
+
+    if flags.iter().any(|f| f == "--dot") {
+        print!("{}", export::to_dot(&graph, &export_options)?);
+        return Ok(());
+    }
+
+    if flags.iter().any(|f| f == "--mermaid") {
+        print!("{}", export::to_mermaid(&graph, &export_options)?);
+        return Ok(());
+    }
+
+    if flags.iter().any(|f| f == "--orders") {
+        for order in orders::all_orders(&graph) {
+            println!("{}", order.join(","));
+        }
+        return Ok(());
+    }
+
+    // Counting memoises over sets of finished steps, which is too slow for wide graphs to do
+    // unasked.
+    if flags.iter().any(|f| f == "--count") {
+        match orders::count_orders(&graph) {
+            Ok(count) => println!("Valid orders: {}", count),
+            Err(e) => println!("Can't count orders: {}", e),
+        }
+        return Ok(());
+    }
+
+    if let Some(order) = flags.iter().find_map(|f| f.strip_prefix("--check=")) {
+        let order: Vec<&str> = order.split(',').collect();
+        match orders::check_order(&graph, &order) {
+            Ok(()) => println!("Order is valid"),
+            Err(e) => println!("Invalid order: {}", e),
+        }
+        return Ok(());
+    }
+
     println!("Steps: {}", part1(&graph)?);
 
+    let schedule = part2(&graph, 5, &durations)?;
+    debug!("\n{}", schedule);
+    println!("Time: {}", schedule.total_time);
+
+    println!("{}", analysis::analyze(&graph, &durations)?);
+
     Ok(())
 }
@@ -0,0 +1,293 @@
+use crate::{DependencyGraph, Result, Step};
+use failure::{format_err, Fail};
+use petgraph::prelude::*;
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+
+/// Counts every order of the steps which respects the dependencies.
+///
+/// Memoises over the sets of steps already done, so this is only feasible for graphs of up to 64
+/// steps, and only fast when the dependencies leave few such sets.
+pub fn count_orders(graph: &DependencyGraph) -> Result<u128> {
+    if graph.node_count() > 64 {
+        return Err(format_err!(
+            "Can only count orders for up to 64 steps, got {}",
+            graph.node_count()
+        ));
+    }
+
+    // Bitmask of the dependencies of every step.
+    let dependencies: Vec<u64> = graph
+        .node_indices()
+        .map(|idx| {
+            graph
+                .neighbors_directed(idx, Direction::Incoming)
+                .fold(0, |mask, dep| mask | 1_u64 << dep.index())
+        })
+        .collect();
+
+    let all = if graph.node_count() == 64 {
+        u64::MAX
+    } else {
+        (1 << graph.node_count()) - 1
+    };
+
+    let mut memo = HashMap::new();
+    count_from(all, 0, &dependencies, &mut memo)
+}
+
+fn count_from(
+    all: u64,
+    done: u64,
+    dependencies: &[u64],
+    memo: &mut HashMap<u64, u128>,
+) -> Result<u128> {
+    if done == all {
+        return Ok(1);
+    }
+
+    if let Some(count) = memo.get(&done) {
+        return Ok(*count);
+    }
+
+    let mut count: u128 = 0;
+    for (step, deps) in dependencies.iter().enumerate() {
+        let bit = 1_u64 << step;
+        if done & bit == 0 && deps & !done == 0 {
+            count = count
+                .checked_add(count_from(all, done | bit, dependencies, memo)?)
+                .ok_or_else(|| format_err!("Number of orders doesn't fit in 128 bits"))?;
+        }
+    }
+
+    memo.insert(done, count);
+    Ok(count)
+}
+
+/// Lazily yields every valid order of the steps, in lexicographic order.
+pub struct AllOrders<'a> {
+    graph: &'a DependencyGraph,
+    /// Every step, sorted by name.
+    steps: Vec<NodeIndex>,
+    /// Unfinished dependencies of every step, indexed by node index.
+    remaining_dependencies: Vec<usize>,
+    placed: Vec<bool>,
+    /// Positions in `steps` making up the current order.
+    order: Vec<usize>,
+    started: bool,
+}
+
+pub fn all_orders(graph: &DependencyGraph) -> AllOrders<'_> {
+    let mut steps: Vec<NodeIndex> = graph.node_indices().collect();
+    steps.sort_by_key(|idx| &graph[*idx]);
+
+    AllOrders {
+        graph,
+        remaining_dependencies: graph
+            .node_indices()
+            .map(|idx| graph.edges_directed(idx, Direction::Incoming).count())
+            .collect(),
+        placed: vec![false; steps.len()],
+        steps,
+        order: vec![],
+        started: false,
+    }
+}
+
+impl AllOrders<'_> {
+    fn is_available(&self, position: usize) -> bool {
+        !self.placed[position] && self.remaining_dependencies[self.steps[position].index()] == 0
+    }
+
+    fn place(&mut self, position: usize) {
+        self.placed[position] = true;
+        self.order.push(position);
+        for next in self
+            .graph
+            .neighbors_directed(self.steps[position], Direction::Outgoing)
+        {
+            self.remaining_dependencies[next.index()] -= 1;
+        }
+    }
+
+    fn unplace(&mut self) -> Option<usize> {
+        let position = self.order.pop()?;
+        self.placed[position] = false;
+        for next in self
+            .graph
+            .neighbors_directed(self.steps[position], Direction::Outgoing)
+        {
+            self.remaining_dependencies[next.index()] += 1;
+        }
+        Some(position)
+    }
+
+    /// Greedily completes the current order with the smallest available steps.
+    /// Returns `false` if it gets stuck, which only happens when there is a cycle.
+    fn complete(&mut self) -> bool {
+        while self.order.len() < self.steps.len() {
+            match (0..self.steps.len()).find(|p| self.is_available(*p)) {
+                Some(position) => self.place(position),
+                None => return false,
+            }
+        }
+        true
+    }
+
+    fn current(&self) -> Vec<Step> {
+        self.order
+            .iter()
+            .map(|position| self.graph[self.steps[*position]].clone())
+            .collect()
+    }
+}
+
+impl Iterator for AllOrders<'_> {
+    type Item = Vec<Step>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.started {
+            self.started = true;
+            return if self.complete() {
+                Some(self.current())
+            } else {
+                None
+            };
+        }
+
+        // Undo steps from the end until one of them can be swapped for a larger available step.
+        while let Some(previous) = self.unplace() {
+            if let Some(position) = (previous + 1..self.steps.len()).find(|p| self.is_available(*p))
+            {
+                self.place(position);
+                if self.complete() {
+                    return Some(self.current());
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// Why an order of steps doesn't satisfy the dependency graph.
+#[derive(Debug, PartialEq)]
+pub enum OrderViolation {
+    UnknownStep(Step),
+    DuplicateStep(Step),
+    MissingSteps(Vec<Step>),
+    /// `step`, at `position` in the order, comes before `dependency`.
+    Dependency {
+        step: Step,
+        dependency: Step,
+        position: usize,
+    },
+}
+
+impl Display for OrderViolation {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            OrderViolation::UnknownStep(step) => write!(f, "Unknown step `{}`", step),
+            OrderViolation::DuplicateStep(step) => write!(f, "Step `{}` appears twice", step),
+            OrderViolation::MissingSteps(steps) => {
+                write!(f, "Missing steps: {}", steps.join(", "))
+            }
+            OrderViolation::Dependency {
+                step,
+                dependency,
+                position,
+            } => write!(
+                f,
+                "Step `{}` at position {} comes before its dependency `{}`",
+                step, position, dependency
+            ),
+        }
+    }
+}
+
+impl Fail for OrderViolation {}
+
+/// Checks that `order` contains every step once, each after all of its dependencies.
+pub fn check_order<S: AsRef<str>>(
+    graph: &DependencyGraph,
+    order: &[S],
+) -> ::std::result::Result<(), OrderViolation> {
+    let nodes: HashMap<&str, NodeIndex> = graph
+        .node_indices()
+        .map(|idx| (graph[idx].as_str(), idx))
+        .collect();
+    let mut position_of: HashMap<NodeIndex, usize> = HashMap::new();
+
+    for (position, step) in order.iter().enumerate() {
+        let step = step.as_ref();
+        let idx = *nodes
+            .get(step)
+            .ok_or_else(|| OrderViolation::UnknownStep(step.to_owned()))?;
+
+        if position_of.insert(idx, position).is_some() {
+            return Err(OrderViolation::DuplicateStep(step.to_owned()));
+        }
+
+        let mut missing_dependencies: Vec<NodeIndex> = graph
+            .neighbors_directed(idx, Direction::Incoming)
+            .filter(|dep| !position_of.contains_key(dep))
+            .collect();
+        missing_dependencies.sort_by_key(|dep| &graph[*dep]);
+
+        if let Some(dep) = missing_dependencies.first() {
+            return Err(OrderViolation::Dependency {
+                step: step.to_owned(),
+                dependency: graph[*dep].clone(),
+                position,
+            });
+        }
+    }
+
+    if position_of.len() < graph.node_count() {
+        let mut missing: Vec<Step> = graph
+            .node_indices()
+            .filter(|idx| !position_of.contains_key(idx))
+            .map(|idx| graph[idx].clone())
+            .collect();
+        missing.sort();
+        return Err(OrderViolation::MissingSteps(missing));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_orders() {
+    let graph = crate::build_dependency_graph(crate::EXAMPLE).unwrap();
+
+    assert_eq!(count_orders(&graph).unwrap(), 8);
+
+    let orders: Vec<String> = all_orders(&graph).map(|order| order.concat()).collect();
+    assert_eq!(orders.len(), 8);
+    assert_eq!(orders[0], crate::part1(&graph).unwrap());
+    assert_eq!(orders[7], "CFADBE");
+    for order in all_orders(&graph) {
+        assert_eq!(check_order(&graph, &order), Ok(()));
+    }
+
+    assert_eq!(
+        check_order(&graph, &["C", "B", "A", "D", "F", "E"]),
+        Err(OrderViolation::Dependency {
+            step: "B".to_owned(),
+            dependency: "A".to_owned(),
+            position: 1,
+        })
+    );
+    assert_eq!(
+        check_order(&graph, &["C", "A", "B"]),
+        Err(OrderViolation::MissingSteps(vec![
+            "D".to_owned(),
+            "E".to_owned(),
+            "F".to_owned()
+        ]))
+    );
+    assert_eq!(
+        check_order(&graph, &["C", "C"]),
+        Err(OrderViolation::DuplicateStep("C".to_owned()))
+    );
+}
@@ -0,0 +1,221 @@
+use crate::{DependencyGraph, Result, Step};
+use failure::{format_err, Error};
+use log::debug;
+use petgraph::prelude::*;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+/// How long each step takes to complete, in seconds.
+pub enum Durations {
+    /// `base` seconds plus the step's position in the alphabet (`A` = 1), for single letter steps.
+    Alphabetical { base: u32 },
+    /// An explicit duration for every step.
+    Table(HashMap<Step, u32>),
+    /// Every step takes the same time.
+    Uniform(u32),
+}
+
+impl Durations {
+    pub fn of(&self, step: &str) -> Result<u32> {
+        let duration = match self {
+            Durations::Alphabetical { base } => {
+                let mut chars = step.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) if c.is_ascii_uppercase() => base + (c as u32 - 'A' as u32 + 1),
+                    _ => {
+                        return Err(format_err!(
+                            "Step `{}` is not a single uppercase letter, provide a duration table",
+                            step
+                        ))
+                    }
+                }
+            }
+            Durations::Table(table) => *table
+                .get(step)
+                .ok_or_else(|| format_err!("No duration given for step `{}`", step))?,
+            Durations::Uniform(seconds) => *seconds,
+        };
+
+        if duration == 0 {
+            return Err(format_err!("Step `{}` must take at least one second", step));
+        }
+
+        Ok(duration)
+    }
+}
+
+impl FromStr for Durations {
+    type Err = Error;
+
+    /// Parses a duration table with one `<step> <seconds>` pair per line.
+    fn from_str(s: &str) -> Result<Self> {
+        let mut table = HashMap::new();
+
+        for line in s.lines().filter(|line| !line.trim().is_empty()) {
+            let mut parts = line.split_whitespace();
+            match (parts.next(), parts.next(), parts.next()) {
+                (Some(step), Some(seconds), None) => {
+                    table.insert(step.to_owned(), seconds.parse()?);
+                }
+                _ => return Err(format_err!("Expected `<step> <seconds>`, got `{}`", line)),
+            }
+        }
+
+        Ok(Durations::Table(table))
+    }
+}
+
+/// The outcome of running all steps with a fixed number of workers.
+#[derive(Debug)]
+pub struct Schedule {
+    pub total_time: u32,
+    /// What every worker is doing during each second, `None` when idle.
+    pub timeline: Vec<Vec<Option<Step>>>,
+}
+
+impl Display for Schedule {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let workers = self.timeline.first().map_or(0, |row| row.len());
+        let width = self
+            .timeline
+            .iter()
+            .flatten()
+            .flatten()
+            .map(|step| step.len())
+            .max()
+            .unwrap_or(1)
+            .max("Worker 10".len());
+
+        write!(f, "{:<8}", "Second")?;
+        for worker in 1..=workers {
+            write!(f, "{:<w$}", format!("Worker {}", worker), w = width + 2)?;
+        }
+        writeln!(f, "Done")?;
+
+        let mut done = String::new();
+
+        for (second, row) in self.timeline.iter().enumerate() {
+            write!(f, "{:<8}", second)?;
+            for slot in row.iter() {
+                write!(f, "{:<w$}", slot.as_deref().unwrap_or("."), w = width + 2)?;
+            }
+            writeln!(f, "{}", done)?;
+
+            // A step is done once its worker moves on to something else.
+            let next = self.timeline.get(second + 1);
+            for (worker, slot) in row.iter().enumerate() {
+                if let Some(step) = slot {
+                    if next.is_none_or(|next| next[worker].as_ref() != Some(step)) {
+                        done.push_str(step);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Simulates `workers` workers picking up available steps as soon as their dependencies finish.
+/// When several steps are available at once, the smallest according to `compare` goes first.
+pub fn simulate<F>(
+    graph: &DependencyGraph,
+    workers: usize,
+    durations: &Durations,
+    compare: F,
+) -> Result<Schedule>
+where
+    F: Fn(&Step, &Step) -> Ordering,
+{
+    if workers == 0 {
+        return Err(format_err!("At least one worker is needed"));
+    }
+
+    let mut remaining_dependencies: HashMap<NodeIndex, usize> = graph
+        .node_indices()
+        .map(|idx| (idx, graph.edges_directed(idx, Direction::Incoming).count()))
+        .collect();
+
+    let mut available: Vec<NodeIndex> = remaining_dependencies
+        .iter()
+        .filter(|(_, deps)| **deps == 0)
+        .map(|(idx, _)| *idx)
+        .collect();
+
+    // Each worker holds the step it is working on and the seconds left for it.
+    let mut in_progress: Vec<Option<(NodeIndex, u32)>> = vec![None; workers];
+    let mut timeline = vec![];
+    let mut finished = 0;
+
+    while finished < graph.node_count() {
+        // Cheapest step on top of the stack.
+        available.sort_by(|a, b| compare(&graph[*b], &graph[*a]));
+
+        for slot in in_progress.iter_mut().filter(|slot| slot.is_none()) {
+            match available.pop() {
+                Some(idx) => *slot = Some((idx, durations.of(&graph[idx])?)),
+                None => break,
+            }
+        }
+
+        if in_progress.iter().all(Option::is_none) {
+            return Err(format_err!(
+                "No step can start after {} seconds, the dependencies contain a cycle",
+                timeline.len()
+            ));
+        }
+
+        timeline.push(
+            in_progress
+                .iter()
+                .map(|slot| slot.map(|(idx, _)| graph[idx].clone()))
+                .collect(),
+        );
+
+        for slot in in_progress.iter_mut() {
+            if let Some((idx, seconds_left)) = slot {
+                *seconds_left -= 1;
+
+                if *seconds_left == 0 {
+                    debug!("{} done after {} seconds", graph[*idx], timeline.len());
+                    for next in graph.neighbors_directed(*idx, Direction::Outgoing) {
+                        let deps = remaining_dependencies
+                            .get_mut(&next)
+                            .expect("Every step is counted");
+                        *deps -= 1;
+                        if *deps == 0 {
+                            available.push(next);
+                        }
+                    }
+
+                    finished += 1;
+                    *slot = None;
+                }
+            }
+        }
+    }
+
+    Ok(Schedule {
+        total_time: timeline.len() as u32,
+        timeline,
+    })
+}
+
+#[test]
+fn test_simulate() {
+    let graph = crate::build_dependency_graph(crate::EXAMPLE).unwrap();
+    let schedule = simulate(&graph, 2, &Durations::Alphabetical { base: 0 }, Step::cmp).unwrap();
+
+    assert_eq!(schedule.total_time, 15);
+    assert_eq!(
+        schedule.timeline[3],
+        vec![Some("A".to_owned()), Some("F".to_owned())]
+    );
+    assert_eq!(schedule.timeline[14], vec![Some("E".to_owned()), None]);
+
+    let table = Durations::from_str("A 1\nB 1\nC 1\nD 1\nE 1\nF 10\n").unwrap();
+    let schedule = simulate(&graph, 1, &table, Step::cmp).unwrap();
+    assert_eq!(schedule.total_time, 15);
+}
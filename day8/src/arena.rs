@@ -0,0 +1,229 @@
+use crate::{checked_sum, node_value, MetadataSumOverflow, Node, ValueOverflow};
+use std::result;
+
+/// Identifies a node within its `Tree`. Ids are assigned in pre-order, so the root is always 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(usize);
+
+impl NodeId {
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+#[derive(Debug)]
+pub struct ArenaNode {
+    pub parent: Option<NodeId>,
+    pub children: Vec<NodeId>,
+    pub metadata: Vec<i32>,
+    pub depth: usize,
+    /// Pre-order places every subtree in a contiguous range of ids, this is where it ends.
+    subtree_end: usize,
+}
+
+/// The license tree stored in a flat arena, with parent links and stable node ids.
+#[derive(Debug)]
+pub struct Tree {
+    nodes: Vec<ArenaNode>,
+}
+
+/// A bottom-up fold over a subtree, every node is visited after all of its children.
+pub trait Visitor {
+    type Output;
+
+    fn visit(&mut self, tree: &Tree, id: NodeId, children: Vec<Self::Output>) -> Self::Output;
+}
+
+impl From<&Node> for Tree {
+    fn from(root: &Node) -> Self {
+        let mut nodes: Vec<ArenaNode> = vec![];
+        let mut stack: Vec<(&Node, Option<NodeId>, usize)> = vec![(root, None, 0)];
+
+        while let Some((node, parent, depth)) = stack.pop() {
+            let id = NodeId(nodes.len());
+
+            if let Some(parent) = parent {
+                nodes[parent.0].children.push(id);
+            }
+
+            nodes.push(ArenaNode {
+                parent,
+                children: vec![],
+                metadata: node.metadata.clone(),
+                depth,
+                subtree_end: 0,
+            });
+
+            if let Some(ref children) = node.children {
+                stack.extend(children.iter().rev().map(|c| (c, Some(id), depth + 1)));
+            }
+        }
+
+        // Children come right after their parent, so every subtree ends where its last child's does.
+        for i in (0..nodes.len()).rev() {
+            nodes[i].subtree_end = match nodes[i].children.last() {
+                Some(last) => nodes[last.0].subtree_end,
+                None => i + 1,
+            };
+        }
+
+        Tree { nodes }
+    }
+}
+
+impl Tree {
+    pub fn root(&self) -> NodeId {
+        NodeId(0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn node(&self, id: NodeId) -> &ArenaNode {
+        &self.nodes[id.0]
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = NodeId> {
+        (0..self.nodes.len()).map(NodeId)
+    }
+
+    /// Runs `visitor` over the subtree rooted at `id`, without recursion.
+    pub fn fold<V: Visitor>(&self, id: NodeId, visitor: &mut V) -> V::Output {
+        let range = id.0..self.nodes[id.0].subtree_end;
+        let mut outputs: Vec<Option<V::Output>> = range.clone().map(|_| None).collect();
+
+        // In reverse pre-order every child comes before its parent.
+        for i in range.clone().rev() {
+            let children = self.nodes[i]
+                .children
+                .iter()
+                .map(|child| {
+                    outputs[child.0 - range.start]
+                        .take()
+                        .expect("Children are visited before their parent")
+                })
+                .collect();
+
+            outputs[i - range.start] = Some(visitor.visit(self, NodeId(i), children));
+        }
+
+        outputs[0].take().expect("The subtree root was visited")
+    }
+
+    pub fn metadata_sum(&self) -> result::Result<i32, MetadataSumOverflow> {
+        self.subtree_metadata_sum(self.root())
+    }
+
+    pub fn subtree_metadata_sum(&self, id: NodeId) -> result::Result<i32, MetadataSumOverflow> {
+        self.fold(id, &mut MetadataSum)
+    }
+
+    pub fn value(&self) -> result::Result<i32, ValueOverflow> {
+        self.fold(self.root(), &mut Values::default())
+    }
+
+    /// The value of every node, indexed by `NodeId::index`.
+    pub fn values(&self) -> Vec<result::Result<i32, ValueOverflow>> {
+        let mut values = Values {
+            all: vec![Ok(0); self.nodes.len()],
+        };
+        // The root's value is also kept in `all`.
+        let _ = self.fold(self.root(), &mut values);
+        values.all
+    }
+
+    /// Nodes whose value is `value`, leaving out those whose value overflows.
+    pub fn nodes_with_value(&self, value: i32) -> Vec<NodeId> {
+        self.values()
+            .into_iter()
+            .enumerate()
+            .filter(|(_, v)| *v == Ok(value))
+            .map(|(i, _)| NodeId(i))
+            .collect()
+    }
+
+    /// Every node from the root down to `id`, inclusive.
+    pub fn path_to(&self, id: NodeId) -> Vec<NodeId> {
+        let mut path = vec![id];
+        let mut current = id;
+
+        while let Some(parent) = self.nodes[current.0].parent {
+            path.push(parent);
+            current = parent;
+        }
+
+        path.reverse();
+        path
+    }
+}
+
+struct MetadataSum;
+
+impl Visitor for MetadataSum {
+    type Output = result::Result<i32, MetadataSumOverflow>;
+
+    fn visit(&mut self, tree: &Tree, id: NodeId, children: Vec<Self::Output>) -> Self::Output {
+        children
+            .into_iter()
+            .try_fold(checked_sum(&tree.node(id).metadata)?, |sum, child| {
+                sum.checked_add(child?).ok_or(MetadataSumOverflow)
+            })
+    }
+}
+
+/// Computes values bottom-up, so each node's value is computed once.
+#[derive(Default)]
+struct Values {
+    /// Filled in with every visited node's value, when not empty.
+    all: Vec<result::Result<i32, ValueOverflow>>,
+}
+
+impl Visitor for Values {
+    type Output = result::Result<i32, ValueOverflow>;
+
+    fn visit(&mut self, tree: &Tree, id: NodeId, children: Vec<Self::Output>) -> Self::Output {
+        let value = node_value(&tree.node(id).metadata, &children);
+
+        if let Some(slot) = self.all.get_mut(id.0) {
+            *slot = value;
+        }
+
+        value
+    }
+}
+
+#[test]
+fn test_arena() {
+    let node = crate::parse_input("2 3 0 3 10 11 12 1 1 0 1 99 2 1 1 2").unwrap();
+    let tree = Tree::from(&node);
+
+    let (a, b, c, d) = (NodeId(0), NodeId(1), NodeId(2), NodeId(3));
+    assert_eq!(tree.len(), 4);
+    assert_eq!(tree.node(a).children, vec![b, c]);
+    assert_eq!(tree.node(d).parent, Some(c));
+    assert_eq!(tree.node(d).depth, 2);
+
+    assert_eq!(tree.metadata_sum(), node.metadata_sum());
+    assert_eq!(tree.value(), node.value());
+    assert_eq!(tree.values(), vec![Ok(66), Ok(33), Ok(0), Ok(99)]);
+    assert_eq!(tree.nodes_with_value(0), vec![c]);
+    assert_eq!(tree.path_to(d), vec![a, c, d]);
+    assert_eq!(tree.subtree_metadata_sum(c), Ok(101));
+
+    // A custom fold, the height of every subtree.
+    struct Height;
+    impl Visitor for Height {
+        type Output = usize;
+
+        fn visit(&mut self, _: &Tree, _: NodeId, children: Vec<usize>) -> usize {
+            children.into_iter().max().map_or(0, |h| h + 1)
+        }
+    }
+    assert_eq!(tree.fold(a, &mut Height), 2);
+    assert_eq!(tree.fold(b, &mut Height), 0);
+}
@@ -0,0 +1,146 @@
+use crate::Node;
+use std::fmt::{self, Display, Formatter, Write};
+
+/// Work left to do for a node while walking the tree without recursion.
+enum Visit<'a> {
+    Enter(&'a Node),
+    Leave(&'a Node),
+}
+
+fn children(node: &Node) -> &[Node] {
+    node.children.as_deref().unwrap_or(&[])
+}
+
+/// Writes the tree back in the license file format, so that parsing it gives the same tree.
+impl Display for Node {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let mut stack = vec![Visit::Enter(self)];
+        let mut first = true;
+
+        let mut write_number = |f: &mut Formatter, number: i64| {
+            let separator = if first { "" } else { " " };
+            first = false;
+            write!(f, "{}{}", separator, number)
+        };
+
+        while let Some(visit) = stack.pop() {
+            match visit {
+                Visit::Enter(node) => {
+                    write_number(f, children(node).len() as i64)?;
+                    write_number(f, node.metadata.len() as i64)?;
+
+                    stack.push(Visit::Leave(node));
+                    stack.extend(children(node).iter().rev().map(Visit::Enter));
+                }
+                Visit::Leave(node) => {
+                    for entry in node.metadata.iter() {
+                        write_number(f, i64::from(*entry))?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Node {
+    /// An indented outline of the tree, with each node's metadata and value.
+    pub fn pretty(&self) -> String {
+        let mut f = String::new();
+        // Every entry holds the node, the indentation of its children, and the branch leading to it.
+        let mut stack = vec![(self, String::new(), String::new())];
+
+        while let Some((node, indent, branch)) = stack.pop() {
+            let value = match node.value() {
+                Ok(value) => value.to_string(),
+                Err(_) => "too large".to_owned(),
+            };
+            writeln!(f, "{}metadata {:?}, value {}", branch, node.metadata, value)
+                .expect("Writing to a string doesn't fail");
+
+            let children = children(node);
+            for (i, child) in children.iter().enumerate().rev() {
+                let (branch, continuation) = if i == children.len() - 1 {
+                    ("└── ", "    ")
+                } else {
+                    ("├── ", "│   ")
+                };
+
+                stack.push((
+                    child,
+                    format!("{}{}", indent, continuation),
+                    format!("{}{}", indent, branch),
+                ));
+            }
+        }
+
+        f
+    }
+
+    /// The tree as JSON, every node an object with `metadata` and `children` arrays.
+    pub fn to_json(&self) -> String {
+        let mut f = String::new();
+        // Whether each node is the last of its siblings, to know when to close the parent's array.
+        let mut stack = vec![(Visit::Enter(self), true)];
+
+        while let Some((visit, is_last)) = stack.pop() {
+            match visit {
+                Visit::Enter(node) => {
+                    let metadata: Vec<String> = node
+                        .metadata
+                        .iter()
+                        .map(|entry| entry.to_string())
+                        .collect();
+                    write!(f, "{{\"metadata\":[{}],\"children\":[", metadata.join(","))
+                        .expect("Writing to a string doesn't fail");
+
+                    stack.push((Visit::Leave(node), is_last));
+                    let children = children(node);
+                    for (i, child) in children.iter().enumerate().rev() {
+                        stack.push((Visit::Enter(child), i == children.len() - 1));
+                    }
+                }
+                Visit::Leave(_) => {
+                    f.push_str("]}");
+                    if !is_last {
+                        f.push(',');
+                    }
+                }
+            }
+        }
+
+        f
+    }
+}
+
+#[test]
+fn test_round_trip() {
+    let input = "2 3 0 3 10 11 12 1 1 0 1 99 2 1 1 2";
+    let tree = crate::parse_input(&format!("  {}\n", input.replace(' ', "\n  "))).unwrap();
+
+    assert_eq!(tree.to_string(), input);
+    assert_eq!(
+        crate::parse_input(&tree.to_string()).unwrap().to_string(),
+        input
+    );
+}
+
+#[test]
+fn test_pretty_and_json() {
+    let tree = crate::parse_input("2 3 0 3 10 11 12 1 1 0 1 99 2 1 1 2").unwrap();
+
+    assert_eq!(
+        tree.pretty(),
+        "metadata [1, 1, 2], value 66
+├── metadata [10, 11, 12], value 33
+└── metadata [2], value 0
+    └── metadata [99], value 99
+"
+    );
+
+    assert_eq!(
+        tree.to_json(),
+        r#"{"metadata":[1,1,2],"children":[{"metadata":[10,11,12],"children":[]},{"metadata":[2],"children":[{"metadata":[99],"children":[]}]}]}"#
+    );
+}
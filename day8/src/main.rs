@@ -1,15 +1,16 @@
 #![allow(dead_code)]
+mod arena;
+mod format;
+
+use std::convert::TryFrom;
 use std::error::Error;
+use std::fmt::{self, Display, Formatter};
 use std::fs::File;
 use std::io::{BufReader, Read};
 use std::path::PathBuf;
 use std::result;
 
-macro_rules! err {
-    ($($tt:tt)*) => { Err(Box::<Error>::from(format!($($tt)*))) }
-}
-
-pub type Result<T> = result::Result<T, Box<Error>>;
+pub type Result<T> = result::Result<T, Box<dyn Error>>;
 
 #[derive(Debug)]
 struct Node {
@@ -17,68 +18,321 @@ struct Node {
     metadata: Vec<i32>,
 }
 
-impl Node {
-    fn from_input_stream(stream: &mut impl Iterator<Item = i32>) -> Result<Node> {
-        let number_of_children = match stream.next() {
-            Some(value) => value,
-            None => return err!("Unexpected EOF"),
-        };
-        let number_of_metadata_entries = match stream.next() {
-            Some(value) => value,
-            None => return err!("Unexpected EOF"),
-        };
+/// A child whose header has been read, but whose children and metadata are still being parsed.
+struct PartialNode {
+    remaining_children: usize,
+    number_of_metadata_entries: usize,
+    children: Vec<Node>,
+}
+
+#[derive(Debug, PartialEq)]
+enum ParseErrorKind {
+    InvalidNumber(String),
+    UnexpectedEof,
+    NegativeHeader(i32),
+    /// The header asks for more children or metadata than there are numbers left in the input.
+    HeaderTooLarge(i32),
+    TrailingInput {
+        remaining: usize,
+    },
+}
+
+#[derive(Debug, PartialEq)]
+struct ParseError {
+    kind: ParseErrorKind,
+    /// Index of the offending number in the input.
+    token: usize,
+    /// 1-based child positions leading from the root to the node being parsed.
+    path: Vec<usize>,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match &self.kind {
+            ParseErrorKind::InvalidNumber(s) => write!(f, "Invalid number `{}`", s)?,
+            ParseErrorKind::UnexpectedEof => write!(f, "Unexpected EOF")?,
+            ParseErrorKind::NegativeHeader(value) => write!(f, "Negative header value {}", value)?,
+            ParseErrorKind::HeaderTooLarge(value) => write!(
+                f,
+                "Header value {} is larger than the rest of the input",
+                value
+            )?,
+            ParseErrorKind::TrailingInput { remaining } => {
+                write!(f, "{} unexpected numbers after the root node", remaining)?
+            }
+        }
+
+        write!(f, " at token {}, in node root", self.token)?;
+        for position in self.path.iter() {
+            write!(f, " > {}", position)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Error for ParseError {}
+
+/// A node's value is larger than an `i32`, which values doubling at every level reach quickly.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ValueOverflow;
+
+impl Display for ValueOverflow {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "Node value doesn't fit in an i32")
+    }
+}
+
+impl Error for ValueOverflow {}
+
+/// The metadata entries add up to more than an `i32`, or less for negative entries.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct MetadataSumOverflow;
+
+impl Display for MetadataSumOverflow {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "Metadata sum doesn't fit in an i32")
+    }
+}
+
+impl Error for MetadataSumOverflow {}
+
+/// Sums metadata entries, failing instead of wrapping around.
+fn checked_sum<'a>(
+    entries: impl IntoIterator<Item = &'a i32>,
+) -> result::Result<i32, MetadataSumOverflow> {
+    entries
+        .into_iter()
+        .try_fold(0_i32, |sum, entry| sum.checked_add(*entry))
+        .ok_or(MetadataSumOverflow)
+}
+
+/// The value of a node given its children's, which only has to fail if an overflowing child is
+/// actually referred to.
+fn node_value(
+    metadata: &[i32],
+    child_values: &[result::Result<i32, ValueOverflow>],
+) -> result::Result<i32, ValueOverflow> {
+    if child_values.is_empty() {
+        return metadata
+            .iter()
+            .try_fold(0_i32, |sum, entry| sum.checked_add(*entry))
+            .ok_or(ValueOverflow);
+    }
+
+    metadata
+        .iter()
+        // indices are 1-based
+        .filter_map(|position| {
+            let index = usize::try_from(*position).ok()?.checked_sub(1)?;
+            child_values.get(index)
+        })
+        .try_fold(0_i32, |sum, value| {
+            sum.checked_add((*value)?).ok_or(ValueOverflow)
+        })
+}
+
+/// Reads numbers one at a time, keeping track of where the parser is for error reporting.
+struct Tokens<'a> {
+    numbers: &'a [i32],
+    position: usize,
+}
 
-        let mut children = vec![];
-        let mut metadata = vec![];
+impl Tokens<'_> {
+    fn remaining(&self) -> usize {
+        self.numbers.len() - self.position
+    }
 
-        for _ in 0..number_of_children {
-            children.push(Node::from_input_stream(stream)?);
+    fn error(&self, kind: ParseErrorKind, stack: &[PartialNode]) -> ParseError {
+        ParseError {
+            kind,
+            token: self.position,
+            path: path_of(stack),
         }
+    }
 
-        for _ in 0..number_of_metadata_entries {
-            if let Some(number) = stream.next() {
-                metadata.push(number);
+    fn next(&mut self, stack: &[PartialNode]) -> result::Result<i32, ParseError> {
+        match self.numbers.get(self.position) {
+            Some(number) => {
+                self.position += 1;
+                Ok(*number)
             }
+            None => Err(self.error(ParseErrorKind::UnexpectedEof, stack)),
+        }
+    }
+
+    /// Reads a header value, which has to be non-negative and fit in the rest of the input,
+    /// given that every entry it counts takes at least `tokens_per_entry` numbers.
+    fn next_header_value(
+        &mut self,
+        tokens_per_entry: usize,
+        stack: &[PartialNode],
+    ) -> result::Result<usize, ParseError> {
+        let value = self.next(stack)?;
+
+        if value < 0 {
+            self.position -= 1;
+            return Err(self.error(ParseErrorKind::NegativeHeader(value), stack));
         }
 
-        if !children.is_empty() {
-            Ok(Node {
-                children: Some(children),
-                metadata,
-            })
-        } else {
-            Ok(Node {
-                children: None,
-                metadata,
-            })
+        if value as usize * tokens_per_entry > self.remaining() {
+            self.position -= 1;
+            return Err(self.error(ParseErrorKind::HeaderTooLarge(value), stack));
         }
+
+        Ok(value as usize)
     }
+}
+
+/// Positions of every node on the stack, relative to its parent.
+fn path_of(stack: &[PartialNode]) -> Vec<usize> {
+    // A node is pushed after all its previous siblings were completed.
+    stack
+        .iter()
+        .skip(1)
+        .zip(stack.iter())
+        .map(|(_, parent)| parent.children.len() + 1)
+        .collect()
+}
+
+impl Node {
+    /// Parses a tree, rejecting malformed headers, truncated input and anything after the root.
+    fn from_numbers(numbers: &[i32]) -> result::Result<Node, ParseError> {
+        let mut tokens = Tokens {
+            numbers,
+            position: 0,
+        };
 
-    pub fn metadata_sum(&self) -> i32 {
-        let mut sum = self.metadata.iter().sum();
+        // Children are parsed with an explicit stack rather than recursion,
+        // so deeply nested trees don't overflow the call stack.
+        let mut stack = vec![];
+        stack.push(PartialNode::from_header(&mut tokens, &stack)?);
 
-        if let Some(ref children) = self.children {
-            for child in children.iter() {
-                sum += child.metadata_sum()
+        loop {
+            let top = stack.last_mut().expect("Stack is never empty here");
+
+            if top.remaining_children > 0 {
+                top.remaining_children -= 1;
+                let child = PartialNode::from_header(&mut tokens, &stack)?;
+                stack.push(child);
+                continue;
+            }
+
+            let mut metadata = Vec::with_capacity(top.number_of_metadata_entries);
+
+            for _ in 0..top.number_of_metadata_entries {
+                metadata.push(tokens.next(&stack)?);
+            }
+
+            let partial = stack.pop().expect("Stack is never empty here");
+
+            let node = if !partial.children.is_empty() {
+                Node {
+                    children: Some(partial.children),
+                    metadata,
+                }
+            } else {
+                Node {
+                    children: None,
+                    metadata,
+                }
+            };
+
+            match stack.last_mut() {
+                Some(parent) => parent.children.push(node),
+                None if tokens.remaining() > 0 => {
+                    return Err(tokens.error(
+                        ParseErrorKind::TrailingInput {
+                            remaining: tokens.remaining(),
+                        },
+                        &stack,
+                    ))
+                }
+                None => return Ok(node),
+            }
+        }
+    }
+
+    pub fn metadata_sum(&self) -> result::Result<i32, MetadataSumOverflow> {
+        let mut sum = 0_i32;
+        let mut stack = vec![self];
+
+        while let Some(node) = stack.pop() {
+            sum = sum
+                .checked_add(checked_sum(&node.metadata)?)
+                .ok_or(MetadataSumOverflow)?;
+
+            if let Some(ref children) = node.children {
+                stack.extend(children.iter());
             }
         }
 
-        sum
+        Ok(sum)
     }
 
-    pub fn value(&self) -> i32 {
-        if let Some(ref children) = self.children {
-            let mut children_sum = 0;
+    pub fn value(&self) -> result::Result<i32, ValueOverflow> {
+        // Post-order, so every node's value is computed exactly once, after its children's,
+        // no matter how many times its parent's metadata refers to it.
+        let mut values: Vec<result::Result<i32, ValueOverflow>> = vec![];
+        let mut stack = vec![(self, false)];
 
-            for position in self.metadata.iter() {
-                // indices are 1-based
-                if let Some(child) = children.get((*position - 1) as usize) {
-                    children_sum += child.value();
+        while let Some((node, children_done)) = stack.pop() {
+            let children = match node.children {
+                Some(ref children) => children,
+                None => {
+                    values.push(node_value(&node.metadata, &[]));
+                    continue;
                 }
+            };
+
+            if !children_done {
+                stack.push((node, true));
+                stack.extend(children.iter().rev().map(|child| (child, false)));
+                continue;
+            }
+
+            let child_values = values.split_off(values.len() - children.len());
+            values.push(node_value(&node.metadata, &child_values));
+        }
+
+        values.pop().expect("The root's value is computed last")
+    }
+}
+
+impl PartialNode {
+    fn from_header(
+        tokens: &mut Tokens,
+        stack: &[PartialNode],
+    ) -> result::Result<PartialNode, ParseError> {
+        // The node isn't on the stack yet, so add its own position to errors.
+        let position = stack.last().map(|parent| parent.children.len() + 1);
+        let with_position = |mut e: ParseError| {
+            e.path.extend(position);
+            e
+        };
+
+        // Every child takes at least its own header, every metadata entry a single number.
+        let number_of_children = tokens.next_header_value(2, stack).map_err(with_position)?;
+        let number_of_metadata_entries =
+            tokens.next_header_value(1, stack).map_err(with_position)?;
+
+        Ok(PartialNode {
+            remaining_children: number_of_children,
+            number_of_metadata_entries,
+            children: vec![],
+        })
+    }
+}
+
+impl Drop for Node {
+    // The default drop glue recurses into children, which overflows the stack on deep trees.
+    fn drop(&mut self) {
+        let mut stack = self.children.take().unwrap_or_default();
+
+        while let Some(mut node) = stack.pop() {
+            if let Some(children) = node.children.take() {
+                stack.extend(children);
             }
-            children_sum
-        } else {
-            self.metadata.iter().sum()
         }
     }
 }
@@ -86,20 +340,28 @@ impl Node {
 fn parse_input(input: &str) -> Result<Node> {
     let mut numbers: Vec<i32> = vec![];
 
-    for number in input.split_ascii_whitespace() {
-        numbers.push(number.parse()?);
+    for (token, number) in input.split_ascii_whitespace().enumerate() {
+        match number.parse() {
+            Ok(number) => numbers.push(number),
+            Err(_) => {
+                return Err(Box::new(ParseError {
+                    kind: ParseErrorKind::InvalidNumber(number.to_owned()),
+                    token,
+                    path: vec![],
+                }))
+            }
+        }
     }
 
-    let mut numbers = numbers.into_iter();
-    Ok(Node::from_input_stream(&mut numbers)?)
+    Ok(Node::from_numbers(&numbers)?)
 }
 
-fn part1(tree: &Node) -> i32 {
-    tree.metadata_sum()
+fn part1(tree: &Node) -> Result<i32> {
+    Ok(tree.metadata_sum()?)
 }
 
-fn part2(tree: &Node) -> i32 {
-    tree.value()
+fn part2(tree: &Node) -> Result<i32> {
+    Ok(tree.value()?)
 }
 
 #[test]
@@ -107,7 +369,7 @@ fn test_part1() {
     let input = "2 3 0 3 10 11 12 1 1 0 1 99 2 1 1 2";
     let tree = parse_input(input).unwrap();
 
-    assert_eq!(part1(&tree), 138);
+    assert_eq!(part1(&tree).unwrap(), 138);
 }
 
 #[test]
@@ -115,7 +377,103 @@ fn test_part2() {
     let input = "2 3 0 3 10 11 12 1 1 0 1 99 2 1 1 2";
     let tree = parse_input(input).unwrap();
 
-    assert_eq!(part1(&tree), 138);
+    assert_eq!(part1(&tree).unwrap(), 138);
+}
+
+#[test]
+fn test_strict_parsing() {
+    let parse_error = |input: &str| -> ParseError {
+        match parse_input(input) {
+            Ok(tree) => panic!("{} parsed as {:?}", input, tree),
+            Err(e) => *e.downcast::<ParseError>().unwrap(),
+        }
+    };
+
+    // The child's second metadata entry is missing.
+    let e = parse_error("1 1 1 2 0 1 5 7");
+    assert_eq!(e.kind, ParseErrorKind::UnexpectedEof);
+    assert_eq!((e.token, e.path.clone()), (8, vec![1]));
+    assert_eq!(e.to_string(), "Unexpected EOF at token 8, in node root > 1");
+
+    let e = parse_error("1 1 1 1 -1 1 1 1");
+    assert_eq!(e.kind, ParseErrorKind::NegativeHeader(-1));
+    assert_eq!((e.token, e.path), (4, vec![1, 1]));
+
+    let e = parse_error("1 1 0 1000000 1");
+    assert_eq!(e.kind, ParseErrorKind::HeaderTooLarge(1_000_000));
+    assert_eq!((e.token, e.path), (3, vec![1]));
+
+    let e = parse_error("0 1 1 2 3");
+    assert_eq!(e.kind, ParseErrorKind::TrailingInput { remaining: 2 });
+    assert_eq!((e.token, e.path), (3, vec![]));
+
+    let e = parse_error("0 1 x");
+    assert_eq!(e.kind, ParseErrorKind::InvalidNumber("x".to_owned()));
+    assert_eq!(e.token, 2);
+}
+
+#[test]
+fn test_deep_tree() {
+    let depth = 1_000_000;
+    let mut input = "1 1 ".repeat(depth - 1);
+    input.push_str("0 1 5");
+    input.push_str(&" 1".repeat(depth - 1));
+
+    let tree = parse_input(&input).unwrap();
+
+    assert_eq!(part1(&tree).unwrap(), 5 + (depth as i32 - 1));
+    assert_eq!(part2(&tree).unwrap(), 5);
+    assert_eq!(tree.to_string(), input);
+
+    let arena = arena::Tree::from(&tree);
+    assert_eq!(arena.metadata_sum(), tree.metadata_sum());
+    assert_eq!(arena.value(), Ok(5));
+}
+
+#[test]
+fn test_value_is_linear() {
+    // Every node refers to its only child twice, so evaluating each reference separately
+    // takes 2^depth steps.
+    let tree_with_depth = |depth: usize, leaf: i32| {
+        let mut input = "1 2 ".repeat(depth);
+        input.push_str(&format!("0 1 {}", leaf));
+        input.push_str(&" 1 1".repeat(depth));
+        parse_input(&input).unwrap()
+    };
+
+    assert_eq!(tree_with_depth(20, 1).value(), Ok(1 << 20));
+    assert_eq!(tree_with_depth(10_000, 0).value(), Ok(0));
+
+    // Deep trees with a nonzero leaf overflow instead of panicking.
+    let deep = tree_with_depth(40, 1);
+    assert_eq!(deep.value(), Err(ValueOverflow));
+    assert!(part2(&deep).is_err());
+    assert_eq!(arena::Tree::from(&deep).value(), Err(ValueOverflow));
+    assert_eq!(tree_with_depth(30, 1).value(), Ok(1 << 30));
+
+    // Overflowing children only matter when they are referred to.
+    let unreferenced = parse_input(&format!("2 1 {} 0 1 7 2", deep)).unwrap();
+    assert_eq!(unreferenced.value(), Ok(7));
+
+    // Positions that aren't a child, however far off, are skipped.
+    let far_off = format!("1 3 0 1 7 {} 0 1", i32::MIN);
+    assert_eq!(parse_input(&far_off).unwrap().value(), Ok(7));
+}
+
+#[test]
+fn test_metadata_sum_overflow() {
+    let tree = parse_input(&format!("1 1 0 1 {} 1", i32::MAX)).unwrap();
+    assert_eq!(tree.metadata_sum(), Err(MetadataSumOverflow));
+    assert!(part1(&tree).is_err());
+    assert_eq!(
+        arena::Tree::from(&tree).metadata_sum(),
+        Err(MetadataSumOverflow)
+    );
+
+    // Only the total has to fit.
+    let tree = parse_input(&format!("1 2 0 1 {} 1 -1", i32::MAX)).unwrap();
+    assert_eq!(tree.metadata_sum(), Ok(i32::MAX));
+    assert_eq!(arena::Tree::from(&tree).metadata_sum(), Ok(i32::MAX));
 }
 
 fn main() -> Result<()> {
@@ -128,7 +486,7 @@ fn main() -> Result<()> {
     f.read_to_string(&mut input)?;
     let tree = parse_input(&input)?;
 
-    println!("Part 1: {}", part1(&tree));
-    println!("Part 2: {}", part2(&tree));
+    println!("Part 1: {}", part1(&tree)?);
+    println!("Part 2: {}", part2(&tree)?);
     Ok(())
 }
@@ -1,35 +1,354 @@
 #![allow(dead_code)]
-#![feature(vecdeque_rotate)]
-use std::collections::VecDeque;
+mod score;
 
-type Score = usize;
+use crate::score::{ScoreOverflow, ScoreTotal};
+use lazy_static::lazy_static;
+use num_bigint::BigUint;
+use regex::Regex;
+use std::env;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::PathBuf;
+use std::str::FromStr;
 
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+/// Points scored in a single turn, totals are any `ScoreTotal`.
+type Score = u64;
+
+/// The puzzle input, e.g. `10 players; last marble is worth 1618 points`.
+#[derive(Debug, PartialEq)]
+struct Game {
+    players: usize,
+    last_marble: usize,
+}
+
+impl FromStr for Game {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self> {
+        lazy_static! {
+            static ref GAME: Regex =
+                Regex::new(r"(\d+) players?; last marble is worth (\d+) points?")
+                    .expect("This is a valid regex");
+        }
+
+        let m = match GAME.captures(s) {
+            Some(m) => m,
+            None => return Err(From::from(format!("Invalid input {}", s))),
+        };
+        let players = m[1].parse()?;
+        if players == 0 {
+            return Err(From::from(format!(
+                "The game needs at least one player: {}",
+                s
+            )));
+        }
+
+        Ok(Game {
+            players,
+            last_marble: m[2].parse()?,
+        })
+    }
+}
+
+/// The numbers the game is played with, `GameRules::default()` being the puzzle's.
+#[derive(Debug, Clone, PartialEq)]
+struct GameRules {
+    /// Marbles which are a multiple of this are scored instead of placed, 0 means never.
+    scoring_multiple: usize,
+    /// How many marbles counter-clockwise of the current one the removed marble is.
+    removal_offset: usize,
+    /// How many marbles clockwise of the current one new marbles are placed after.
+    placement_offset: usize,
+}
+
+impl Default for GameRules {
+    fn default() -> Self {
+        GameRules {
+            scoring_multiple: 23,
+            removal_offset: 7,
+            placement_offset: 1,
+        }
+    }
+}
+
+/// Something that happened during a turn, `player` being the 0-based index of whoever played.
+#[derive(Debug, Clone, PartialEq)]
+enum Event {
+    Placed {
+        player: usize,
+        marble: u32,
+    },
+    /// The player kept `marble` and took out `removed`, scoring both.
+    Scored {
+        player: usize,
+        marble: u32,
+        removed: Option<u32>,
+        points: Score,
+    },
+}
+
+#[derive(Debug)]
+struct GameResult<S = Score> {
+    /// Index of the player with the highest score, the first one on ties.
+    winner: usize,
+    /// Final score of every player.
+    scores: Vec<S>,
+    /// Every turn in order, if the game was played with a log.
+    events: Option<Vec<Event>>,
+}
+
+impl<S: ScoreTotal> GameResult<S> {
+    fn high_score(&self) -> S {
+        self.scores[self.winner].clone()
+    }
+}
+
+/// Why a game couldn't be played to the end.
+#[derive(Debug, PartialEq)]
+enum GameError {
+    ScoreOverflow(ScoreOverflow),
+    /// Marble values are stored as u32, so the last marble has to fit in one.
+    TooManyMarbles {
+        n_marbles: usize,
+    },
+}
+
+impl Display for GameError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            GameError::ScoreOverflow(e) => e.fmt(f),
+            GameError::TooManyMarbles { n_marbles } => write!(
+                f,
+                "Can't play with {} marbles, marble values have to fit in a u32",
+                n_marbles
+            ),
+        }
+    }
+}
+
+impl Error for GameError {}
+
+impl From<ScoreOverflow> for GameError {
+    fn from(e: ScoreOverflow) -> Self {
+        GameError::ScoreOverflow(e)
+    }
+}
+
+/// The marbles on the board, as a doubly linked ring.
+/// Every marble has a distinct value, so the links are stored in arrays indexed by marble value.
+struct Circle {
+    next: Vec<u32>,
+    prev: Vec<u32>,
+    current: u32,
+    len: usize,
+    /// The marble the circle is printed from, marble 0 as long as it's there.
+    first: u32,
+}
+
+impl Circle {
+    /// A circle holding only marble 0, with room for marbles up to `n_marbles`.
+    /// `None` if `n_marbles` doesn't fit in a u32.
+    pub fn new(n_marbles: usize) -> Option<Self> {
+        if n_marbles >= u32::MAX as usize {
+            return None;
+        }
+
+        // Marble 0 starts out linked to itself.
+        Some(Circle {
+            next: vec![0; n_marbles + 1],
+            prev: vec![0; n_marbles + 1],
+            current: 0,
+            len: 1,
+            first: 0,
+        })
+    }
+
+    pub fn clockwise(&mut self, steps: usize) {
+        for _ in 0..steps {
+            self.current = self.next[self.current as usize];
+        }
+    }
+
+    pub fn counter_clockwise(&mut self, steps: usize) {
+        for _ in 0..steps {
+            self.current = self.prev[self.current as usize];
+        }
+    }
+
+    /// Places `marble` clockwise of the current marble, and makes it current.
+    pub fn insert(&mut self, marble: u32) {
+        let before = self.current;
+        let after = self.next[before as usize];
+
+        self.next[marble as usize] = after;
+        self.prev[marble as usize] = before;
+        self.next[before as usize] = marble;
+        self.prev[after as usize] = marble;
+
+        self.current = marble;
+        self.len += 1;
+    }
+
+    /// Takes the current marble out, the one clockwise of it becomes current.
+    /// The last marble in the circle is never removed.
+    pub fn remove(&mut self) -> Option<u32> {
+        if self.len == 1 {
+            return None;
+        }
+
+        let removed = self.current;
+        let before = self.prev[removed as usize];
+        let after = self.next[removed as usize];
+
+        self.next[before as usize] = after;
+        self.prev[after as usize] = before;
+
+        if removed == self.first {
+            self.first = after;
+        }
+        self.current = after;
+        self.len -= 1;
+        Some(removed)
+    }
+}
+
+/// The marbles clockwise from the first one, with the current one in parentheses: `0 (4) 2 1 3`.
+impl Display for Circle {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let mut marble = self.first;
+
+        for i in 0..self.len {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+
+            if marble == self.current {
+                write!(f, "({})", marble)?;
+            } else {
+                write!(f, "{}", marble)?;
+            }
+
+            marble = self.next[marble as usize];
+        }
+
+        Ok(())
+    }
+}
+
+/// Panics if the high score doesn't fit in a `Score`, use `play` to handle that.
 fn marble_game(num_players: usize, n_marbles: usize) -> Score {
-    let mut board = VecDeque::with_capacity(n_marbles + 1);
-    board.push_back(0);
+    marble_game_with_rules(num_players, n_marbles, &GameRules::default())
+}
+
+fn marble_game_with_rules(num_players: usize, n_marbles: usize, rules: &GameRules) -> Score {
+    match play(num_players, n_marbles, rules, false) {
+        Ok(result) => result.high_score(),
+        Err(e) => panic!("{}", e),
+    }
+}
+
+/// Plays the whole game, keeping every turn's event if `log` is set.
+/// Scores are totalled in `S`, which fails the game as soon as a total doesn't fit.
+fn play<S: ScoreTotal>(
+    num_players: usize,
+    n_marbles: usize,
+    rules: &GameRules,
+    log: bool,
+) -> std::result::Result<GameResult<S>, GameError> {
+    let mut events = if log { Some(vec![]) } else { None };
+
+    let scores = play_with(num_players, n_marbles, rules, |event, _| {
+        if let Some(ref mut events) = events {
+            events.push(event.clone());
+        }
+    })?;
 
-    let mut scores = vec![0; num_players];
+    Ok(GameResult {
+        winner: winner(&scores),
+        scores,
+        events,
+    })
+}
+
+/// The circle after every turn, one line each, in the puzzle's `[p] 0 (4) 2 1 3` format.
+/// Meant for small games, the output grows quadratically with the number of marbles.
+fn render_game(num_players: usize, n_marbles: usize, rules: &GameRules) -> String {
+    let mut rendered = format!("[-] {}\n", Circle::new(0).expect("An empty circle fits"));
+
+    play_with::<BigUint, _>(num_players, n_marbles, rules, |event, board| {
+        let player = match event {
+            Event::Placed { player, .. } | Event::Scored { player, .. } => player,
+        };
+        rendered.push_str(&format!("[{}] {}\n", player + 1, board));
+    })
+    .expect("Big integer scores don't overflow");
+
+    rendered
+}
+
+/// Plays the game, calling `observe` with the board after every turn, and returns the final scores.
+fn play_with<S: ScoreTotal, F: FnMut(&Event, &Circle)>(
+    num_players: usize,
+    n_marbles: usize,
+    rules: &GameRules,
+    mut observe: F,
+) -> std::result::Result<Vec<S>, GameError> {
+    assert!(num_players > 0, "The game needs at least one player");
+
+    let mut board = Circle::new(n_marbles).ok_or(GameError::TooManyMarbles { n_marbles })?;
+
+    let mut scores = vec![S::default(); num_players];
 
     for marble in 1..=n_marbles {
-        if marble % 23 == 0 {
-            // Pop the marble 7 positions counter-clockwise
-            board.rotate_right(7);
+        let player = (marble - 1) % num_players;
 
-            scores[marble % num_players] +=
-                marble + board.pop_back().expect("There is always a marble");
+        let event = if rules.scoring_multiple != 0 && marble % rules.scoring_multiple == 0 {
+            // Pop the marble `removal_offset` positions counter-clockwise,
+            // the adjacent one becomes current
+            board.counter_clockwise(rules.removal_offset);
 
-            // Mark adjacent one as current
-            board.rotate_left(1);
+            let removed = board.remove();
+            let points = marble as Score + Score::from(removed.unwrap_or(0));
+            scores[player] = scores[player].add_points(points).ok_or(ScoreOverflow {
+                player,
+                marble: marble as u32,
+            })?;
+
+            Event::Scored {
+                player,
+                marble: marble as u32,
+                removed,
+                points,
+            }
         } else {
-            board.rotate_left(1);
-            board.push_back(marble);
+            board.clockwise(rules.placement_offset);
+            board.insert(marble as u32);
+
+            Event::Placed {
+                player,
+                marble: marble as u32,
+            }
+        };
+
+        observe(&event, &board);
+    }
+
+    Ok(scores)
+}
+
+fn winner<S: Ord>(scores: &[S]) -> usize {
+    let mut winner = 0;
+
+    for (player, score) in scores.iter().enumerate() {
+        if *score > scores[winner] {
+            winner = player;
         }
     }
 
-    *scores
-        .iter()
-        .max()
-        .expect("There is always some score present")
+    winner
 }
 
 #[test]
@@ -39,7 +358,148 @@ fn test_part1() {
     assert_eq!(marble_game(10, 1618), 8317);
 }
 
-fn main() {
-    println!("Part 1: {}", marble_game(426, 72058));
-    println!("Part 2: {}", marble_game(426, 7_205_800));
+#[test]
+fn test_game_parsing() {
+    let game = Game::from_str("10 players; last marble is worth 1618 points\n").unwrap();
+    assert_eq!(
+        game,
+        Game {
+            players: 10,
+            last_marble: 1618
+        }
+    );
+    assert!(Game::from_str("10 players; last marble is worth").is_err());
+    assert!(Game::from_str("0 players; last marble is worth 1618 points").is_err());
+}
+
+#[test]
+fn test_rules() {
+    assert_eq!(
+        marble_game_with_rules(9, 25, &GameRules::default()),
+        marble_game(9, 25)
+    );
+
+    // Without scoring marbles nobody ever scores.
+    let no_scoring = GameRules {
+        scoring_multiple: 0,
+        ..GameRules::default()
+    };
+    assert_eq!(marble_game_with_rules(9, 100, &no_scoring), 0);
+
+    // Scoring every 2nd marble, removing the current one: 0 (1), then marble 2 takes out 1.
+    let every_other = GameRules {
+        scoring_multiple: 2,
+        removal_offset: 0,
+        placement_offset: 1,
+    };
+    assert_eq!(marble_game_with_rules(2, 2, &every_other), 3);
+}
+
+#[test]
+fn test_replay() {
+    let result = play::<Score>(9, 25, &GameRules::default(), true).unwrap();
+    assert_eq!(result.winner, 4);
+    assert_eq!(result.high_score(), 32);
+    assert_eq!(result.scores.iter().sum::<Score>(), 32);
+
+    let events = result.events.unwrap();
+    assert_eq!(events.len(), 25);
+    assert_eq!(
+        events[22],
+        Event::Scored {
+            player: 4,
+            marble: 23,
+            removed: Some(9),
+            points: 32
+        }
+    );
+    assert_eq!(
+        events[23],
+        Event::Placed {
+            player: 5,
+            marble: 24
+        }
+    );
+    assert!(play::<Score>(9, 25, &GameRules::default(), false)
+        .unwrap()
+        .events
+        .is_none());
+
+    let rendered = render_game(9, 25, &GameRules::default());
+    let lines: Vec<&str> = rendered.lines().collect();
+    assert_eq!(lines.len(), 26);
+    assert_eq!(lines[0], "[-] (0)");
+    assert_eq!(lines[4], "[4] 0 (4) 2 1 3");
+    assert_eq!(
+        lines[23],
+        "[5] 0 16 8 17 4 18 (19) 2 20 10 21 5 22 11 1 12 6 13 3 14 7 15"
+    );
+    assert_eq!(
+        lines[25],
+        "[7] 0 16 8 17 4 18 19 2 24 20 (25) 10 21 5 22 11 1 12 6 13 3 14 7 15"
+    );
+}
+
+#[test]
+fn test_score_overflow() {
+    let rules = GameRules::default();
+
+    let big = play::<BigUint>(10, 1618, &rules, false).unwrap();
+    assert_eq!(big.high_score(), BigUint::from(8317_u32));
+    assert_eq!(
+        big.winner,
+        play::<u16>(10, 1618, &rules, false).unwrap().winner
+    );
+
+    // Player 10 first scores with marble 230, which with the marble it removes overflows a byte.
+    let e = play::<u8>(10, 1618, &rules, false).unwrap_err();
+    assert_eq!(
+        e,
+        GameError::ScoreOverflow(ScoreOverflow {
+            player: 9,
+            marble: 230
+        })
+    );
+    assert!(e.to_string().starts_with("Score of player 10 overflowed"));
+}
+
+#[test]
+fn test_too_many_marbles() {
+    let n_marbles = u32::MAX as usize;
+    let e = play::<Score>(10, n_marbles, &GameRules::default(), false).unwrap_err();
+    assert_eq!(e, GameError::TooManyMarbles { n_marbles });
+}
+
+fn main() -> Result<()> {
+    let input = PathBuf::from("/Users/omerba/Workspace/AOC2018/day9/input/game");
+    let f = File::open(input)?;
+    let mut f = BufReader::new(f);
+
+    let mut input = String::new();
+
+    f.read_to_string(&mut input)?;
+    let game = Game::from_str(&input)?;
+
+    // Pass `--big` to total scores with arbitrary precision instead of failing on overflow.
+    if env::args().any(|arg| arg == "--big") {
+        print_results::<BigUint>(&game)
+    } else {
+        print_results::<Score>(&game)
+    }
+}
+
+fn print_results<S: ScoreTotal>(game: &Game) -> Result<()> {
+    let rules = GameRules::default();
+
+    let result = play::<S>(game.players, game.last_marble, &rules, false)?;
+    println!(
+        "Part 1: {} (player {})",
+        result.high_score(),
+        result.winner + 1
+    );
+
+    let result = play::<S>(game.players, game.last_marble * 100, &rules, false)?;
+    println!("Part 2: {}", result.high_score());
+
+    Ok(())
 }
@@ -0,0 +1,52 @@
+use crate::Score;
+use num_bigint::BigUint;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+/// A running score total, which refuses to silently wrap around.
+pub trait ScoreTotal: Clone + Ord + Default + Display {
+    /// Adds the points of a turn, `None` if the total doesn't fit anymore.
+    fn add_points(&self, points: Score) -> Option<Self>;
+}
+
+macro_rules! checked_score_total {
+    ($($t:ty),*) => {
+        $(
+            impl ScoreTotal for $t {
+                fn add_points(&self, points: Score) -> Option<Self> {
+                    let points = <$t>::try_from(points).ok()?;
+                    self.checked_add(points)
+                }
+            }
+        )*
+    };
+}
+
+checked_score_total!(u8, u16, u32, u64, u128, usize);
+
+/// Arbitrary precision, for games too long for any fixed size total.
+impl ScoreTotal for BigUint {
+    fn add_points(&self, points: Score) -> Option<Self> {
+        Some(self + points)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ScoreOverflow {
+    pub player: usize,
+    pub marble: u32,
+}
+
+impl Display for ScoreOverflow {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Score of player {} overflowed when scoring marble {}, play with big integer scores instead",
+            self.player + 1,
+            self.marble
+        )
+    }
+}
+
+impl Error for ScoreOverflow {}